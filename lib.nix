@@ -14,10 +14,113 @@ let self = {
         type = with lib.types; nullOr str;
         default = null;
       };
+      options.stack = lib.mkOption {
+        description = "Placeholder for the value this many values deep in the stack";
+        type = with lib.types; nullOr int;
+        default = null;
+      };
+      options.env = lib.mkOption {
+        description = "Placeholder for an environment variable";
+        type = with lib.types; nullOr str;
+        default = null;
+      };
+      options.var = lib.mkOption {
+        description = "Placeholder for a variable";
+        type = with lib.types; nullOr str;
+        default = null;
+      };
+      options.text = lib.mkOption {
+        description = "Placeholder for the selected row's text";
+        type = with lib.types; nullOr bool;
+        default = null;
+      };
+      options.retv = lib.mkOption {
+        description = "Placeholder for rofi's ROFI_RETV";
+        type = with lib.types; nullOr bool;
+        default = null;
+      };
+      options.status = lib.mkOption {
+        description = "Placeholder for the exit status of exec";
+        type = with lib.types; nullOr bool;
+        default = null;
+      };
+      options.output = lib.mkOption {
+        description = "Placeholder for the output of exec";
+        type = with lib.types; nullOr bool;
+        default = null;
+      };
     };
     bashStr = lib.types.either lib.types.str bashValue;
     bashBool = lib.types.either lib.types.bool bashValue;
     bashInt = lib.types.either lib.types.int bashValue;
+    condition = lib.types.submodule {
+      options.stack_len_at_least = lib.mkOption {
+        default = null;
+        type = lib.types.nullOr bashInt;
+      };
+      options.input_matches = lib.mkOption {
+        default = null;
+        type = lib.types.nullOr bashStr;
+      };
+      options.top_equals = lib.mkOption {
+        default = null;
+        type = lib.types.nullOr bashStr;
+      };
+      options.input_number = lib.mkOption {
+        description = "The user input is a number in this range";
+        default = null;
+        type = lib.types.nullOr (lib.types.submodule {
+          options.min = lib.mkOption {
+            default = null;
+            type = lib.types.nullOr lib.types.number;
+          };
+          options.max = lib.mkOption {
+            default = null;
+            type = lib.types.nullOr lib.types.number;
+          };
+        });
+      };
+    };
+    frame = lib.types.submodule {
+      options.script = lib.mkOption {
+        type = stringListNull;
+      };
+      options.args = lib.mkOption {
+        description = "Arguments to pass to the script before the stack values, or how many stack values the script can see";
+        default = [ ];
+        type = lib.types.either lib.types.int (lib.types.listOf stringListNull);
+      };
+      options.results = lib.mkOption {
+        description = "How many values to return to the caller";
+        default = null;
+        type = lib.types.nullOr lib.types.int;
+      };
+      options.cwd = lib.mkOption {
+        description = "Working directory of the script";
+        default = null;
+        type = lib.types.nullOr bashStr;
+      };
+      options.env = lib.mkOption {
+        description = "Extra environment variables for the script";
+        default = { };
+        type = lib.types.attrsOf stringListNull;
+      };
+    };
+    frameOrString = lib.types.either (lib.types.addCheck frame (x: builtins.isAttrs x && x ? script)) stringListNull;
+    frames = lib.types.either (lib.types.listOf frameOrString) frameOrString;
+    notification = lib.types.submodule {
+      options.summary = lib.mkOption {
+        type = stringListNull;
+      };
+      options.body = lib.mkOption {
+        default = null;
+        type = stringListNull;
+      };
+      options.urgency = lib.mkOption {
+        default = null;
+        type = lib.types.nullOr (lib.types.enum [ "low" "normal" "critical" ]);
+      };
+    };
     row' = full: lib.types.submodule {
       options = lib.optionalAttrs full {
         enable = lib.mkOption {
@@ -40,11 +143,11 @@ let self = {
         };
         jump = lib.mkOption {
           default = [ ];
-          type = stringListNull;
+          type = frames;
         };
         goto = lib.mkOption {
           default = [ ];
-          type = stringListNull;
+          type = frames;
         };
         return = lib.mkOption {
           default = 0;
@@ -58,14 +161,84 @@ let self = {
           default = false;
           type = bashBool;
         };
+        capture = lib.mkOption {
+          description = "What to do with the output of exec (push, lines or message)";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "push" "lines" "message" ]);
+        };
+        on_success = lib.mkOption {
+          description = "Operations to apply instead if exec succeeds";
+          default = null;
+          type = lib.types.nullOr fallbackRow;
+        };
+        on_failure = lib.mkOption {
+          description = "Operations to apply instead if exec fails";
+          default = null;
+          type = lib.types.nullOr fallbackRow;
+        };
+        notify = lib.mkOption {
+          description = "Desktop notification to show once exec completes";
+          default = null;
+          type = lib.types.nullOr notification;
+        };
+        toggle = lib.mkOption {
+          description = "Value to add to or remove from the selected values";
+          default = [ ];
+          type = stringListNull;
+        };
+        apply = lib.mkOption {
+          description = "Push the selected values onto the stack or pass them to exec, and clear them";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "push" "exec" ]);
+        };
+        page = lib.mkOption {
+          description = "Switch to the next or previous page of a menu with page_size";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "next" "previous" ]);
+        };
+        confirm = lib.mkOption {
+          description = "Message of a yes/no menu to show before applying the operations";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
         menu = lib.mkOption {
           default = null;
           type = lib.types.nullOr (submenu' false);
         };
+        set = lib.mkOption {
+          description = "Variables to set in the current call stack frame";
+          default = { };
+          type = lib.types.attrsOf stringListNull;
+        };
+        unset = lib.mkOption {
+          description = "Variables to remove from the current call stack frame";
+          default = [ ];
+          type = lib.types.listOf lib.types.str;
+        };
+        "if" = lib.mkOption {
+          description = "Only apply the operations if all of these conditions hold";
+          default = null;
+          type = lib.types.nullOr condition;
+        };
+        "else" = lib.mkOption {
+          description = "Operations to apply instead if the condition doesn't hold";
+          default = null;
+          type = lib.types.nullOr fallbackRow;
+        };
+        keys = lib.mkOption {
+          description = "Operations to execute on custom keybindings (kb-custom-1 to kb-custom-19) instead of the default ones";
+          default = { };
+          type = lib.types.attrsOf fallbackRow;
+        };
       } // lib.optionalAttrs full {
         text = lib.mkOption {
           type = bashStr;
         };
+        display = lib.mkOption {
+          description = "Text to show instead of the row text (which is still used for filtering)";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
         icon = lib.mkOption {
           default = null;
           type = lib.types.nullOr bashStr;
@@ -86,6 +259,35 @@ let self = {
           default = false;
           type = bashBool;
         };
+        permanent = lib.mkOption {
+          default = false;
+          type = bashBool;
+        };
+        kind = lib.mkOption {
+          description = "Show the row as a checkbox or radio button for a setting";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "checkbox" "radio" ]);
+        };
+        state = lib.mkOption {
+          description = "Whether the setting is on";
+          default = null;
+          type = lib.types.nullOr bashBool;
+        };
+        state_cmd = lib.mkOption {
+          description = "Command that exits successfully if the setting is on";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
+        on = lib.mkOption {
+          description = "Command to switch the setting on";
+          default = [ ];
+          type = stringListNull;
+        };
+        off = lib.mkOption {
+          description = "Command to switch the setting off";
+          default = [ ];
+          type = stringListNull;
+        };
       };
     };
     row = row' true;
@@ -126,6 +328,72 @@ let self = {
           default = false;
           type = bashBool;
         };
+        session = lib.mkOption {
+          description = "Name of the session to save the menu state to";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
+        hotkeys = lib.mkOption {
+          default = false;
+          type = bashBool;
+        };
+        urgent = lib.mkOption {
+          description = "Indices of rows to mark as urgent";
+          default = null;
+          type = lib.types.nullOr (lib.types.listOf bashInt);
+        };
+        active = lib.mkOption {
+          description = "Indices of rows to mark as active";
+          default = null;
+          type = lib.types.nullOr (lib.types.listOf bashInt);
+        };
+        theme = lib.mkOption {
+          description = "rofi theme snippet to apply to this menu";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
+        keep_filter = lib.mkOption {
+          default = false;
+          type = bashBool;
+        };
+        sort = lib.mkOption {
+          description = "Put the most frequently and recently selected rows first";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "frecency" ]);
+        };
+        page_size = lib.mkOption {
+          description = "How many rows to show at once";
+          default = null;
+          type = lib.types.nullOr bashInt;
+        };
+        kind = lib.mkOption {
+          description = "Make this a menu for changing a number";
+          default = null;
+          type = lib.types.nullOr (lib.types.enum [ "number" ]);
+        };
+        min = lib.mkOption {
+          default = null;
+          type = lib.types.nullOr lib.types.number;
+        };
+        max = lib.mkOption {
+          default = null;
+          type = lib.types.nullOr lib.types.number;
+        };
+        step = lib.mkOption {
+          description = "How much the generated rows change the number by";
+          default = null;
+          type = lib.types.nullOr lib.types.number;
+        };
+        value_cmd = lib.mkOption {
+          description = "Command that prints the current number";
+          default = null;
+          type = lib.types.nullOr bashStr;
+        };
+        set = lib.mkOption {
+          description = "Command to set the number, user input (null) is replaced with the new number";
+          default = [ ];
+          type = stringListNull;
+        };
         fallback = lib.mkOption {
           default = null;
           type = lib.types.nullOr fallbackRow;
@@ -135,18 +403,29 @@ let self = {
     submenu = submenu' true;
   };
 
+  placeholders = [ "stack" "env" "var" "text" "retv" "status" "output" ];
   escapeBashVal = x:
     if (x._bash or null) != null then ''"$(${x._bash})"''
     else if (x._bashStr or null) != null then ''"$(val "$(${x._bashStr})")"''
+    else if builtins.isAttrs x && x ? script
+      then self.escapeBashAttrs (lib.filterAttrs (_: v: v != null && v != [ ] && v != { }) x)
+    else if builtins.any (k: (x.${k} or null) != null) self.placeholders
+      then lib.escapeShellArg (builtins.toJSON (lib.filterAttrs (k: v: builtins.elem k self.placeholders && v != null) x))
     else if builtins.isList x then "[" + builtins.concatStringsSep "," (map self.escapeBashVal x) + "]"
+    else if builtins.isAttrs x && !(x ? _bash) then self.escapeBashAttrs x
     else lib.escapeShellArg (builtins.toJSON x);
+  escapeBashAttrs = x: "'{'" + builtins.concatStringsSep "','" (lib.mapAttrsToList (k: v: "${lib.escapeShellArg (builtins.toJSON k)}':'${self.escapeBashVal v}") x) + "'}'";
   filterJson = type: val: lib.filterAttrs (k: v: (v.enable or true) && v != (type.getSubOptions { }).${k}.default) val;
 
   compileRow = row: if row._bash != null then row._bash else
     builtins.concatStringsSep " " ([
       "row ${self.escapeBashVal row.text}"
     ] ++ lib.mapAttrsToList
-      (k: v: "${k} ${self.escapeBashVal (if k == "menu" then self.filterJson self.types.submenu v else v)}")
+      (k: v: "${k} ${self.escapeBashVal (if k == "menu" then self.filterJson self.types.submenu v
+        else if k == "keys" then builtins.mapAttrs (_: self.filterJson self.types.fallbackRow) v
+        else if k == "if" || k == "notify" then lib.filterAttrs (_: x: x != null) v
+        else if builtins.elem k [ "else" "on_success" "on_failure" ] then self.filterJson self.types.fallbackRow v
+        else v)}")
       (self.filterJson self.types.row (builtins.removeAttrs row [ "enable" "_bash" "text" ])));
   compileOptions = options: if options._bash != null then options._bash else
     builtins.concatStringsSep " " ([
@@ -65,6 +65,13 @@ vals() {
   jq -cn '$ARGS.positional' --args -- "$@"
 }
 
+# make a list of numbers (e.g. row indices for the urgent/active options)
+# returns: a json list of numbers, one number per argument
+# example: ints 0 2 returns [0,2]
+ints() {
+  jq -cn '$ARGS.positional | map(tonumber)' --args -- "$@"
+}
+
 # convert json5 to json
 # arg1: json5
 # returns: json
@@ -77,3 +84,31 @@ call_stack_len() {
     echo -n "$_CALL_STACK_LEN"
   fi
 }
+
+# run the menu as a long-running server (use `server:<script>` as the call stack entry)
+# arg1: function that writes the menu, called with the stack as arguments
+#       just like a regular script
+serve() {
+  local request fields vars i args
+  while IFS= read -r request; do
+    wrote_options=""
+    # parse the request with a single jq: the call stack length, input,
+    # retv, status, variable count, variables and the stack (top first)
+    mapfile -d '' -t fields < <(jq -j '
+      [(.call_stack | length - 1), .input, .retv, (.status // ""), (.vars | length)]
+      + (.vars | to_entries | map(.key, .value)) + (.stack | reverse)
+      | .[] | tostring, "\u0000"' <<<"$request")
+    _CALL_STACK_LEN="${fields[0]}"
+    RMS_INPUT="${fields[1]}"
+    RMS_RETV="${fields[2]}"
+    RMS_STATUS="${fields[3]}"
+    vars="${fields[4]}"
+    unset "${!RMS_VAR_@}"
+    for ((i = 0; i < vars; i++)); do
+      export "RMS_VAR_${fields[5 + 2 * i]}=${fields[6 + 2 * i]}"
+    done
+    args=("${fields[@]:5 + 2 * vars}")
+    "$1" "${args[@]}" </dev/null
+    echo
+  done
+}
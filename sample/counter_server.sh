@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+. ./lib.sh
+requests=0
+menu() {
+  requests=$((requests + 1))
+  options prompt "$(val "Server >")" message "$(val "Requests served: $requests, stack: $*")"
+  row "$(val "Push")" push "$(val "$requests")"
+  row "$(val "Pop")" pop 1
+  row "$(val "Close")" return null
+}
+serve menu
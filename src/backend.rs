@@ -0,0 +1,167 @@
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    engine::{self, Menu},
+    options::Selection,
+    row::Row,
+    server::Servers,
+    Action, Data, Info,
+};
+
+/// A dmenu-like program that reads rows from stdin and prints the selected one
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Backend {
+    Dmenu,
+    Bemenu,
+    Fuzzel,
+    Wofi,
+}
+
+impl Backend {
+    pub const ITEMS: &'static [&'static str] = &["dmenu", "bemenu", "fuzzel", "wofi"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dmenu" => Some(Self::Dmenu),
+            "bemenu" => Some(Self::Bemenu),
+            "fuzzel" => Some(Self::Fuzzel),
+            "wofi" => Some(Self::Wofi),
+            _ => None,
+        }
+    }
+
+    fn command(self, menu: &Menu, last_selection: Option<usize>) -> Command {
+        let mut cmd = match self {
+            Self::Dmenu => Command::new("dmenu"),
+            Self::Bemenu => Command::new("bemenu"),
+            Self::Fuzzel => {
+                let mut cmd = Command::new("fuzzel");
+                cmd.arg("--dmenu");
+                cmd
+            }
+            Self::Wofi => {
+                let mut cmd = Command::new("wofi");
+                cmd.arg("--dmenu");
+                cmd
+            }
+        };
+        // none of these have a separate message line, so show it in the prompt
+        let prompt = match (&menu.opts.message, &menu.opts.prompt) {
+            (Some(message), Some(prompt)) => Some(format!("{message} {prompt}")),
+            (Some(x), None) | (None, Some(x)) => Some(x.clone()),
+            (None, None) => None,
+        };
+        if let Some(prompt) = prompt {
+            cmd.arg(match self {
+                Self::Dmenu | Self::Bemenu => "-p",
+                Self::Fuzzel | Self::Wofi => "--prompt",
+            });
+            cmd.arg(prompt);
+        }
+        let selection = match menu.opts.selection {
+            Some(Selection::Keep) => last_selection,
+            Some(Selection::Set(x)) => usize::try_from(x).ok(),
+            None => None,
+        };
+        if let Some(sel) = selection {
+            match self {
+                Self::Bemenu => {
+                    cmd.arg("-I").arg(sel.to_string());
+                }
+                Self::Fuzzel => {
+                    cmd.arg("--select-index").arg(sel.to_string());
+                }
+                Self::Dmenu | Self::Wofi => {}
+            }
+        }
+        cmd
+    }
+
+    fn render_row(self, row: &Row) -> String {
+        let mut ret = row.display.as_ref().unwrap_or(&row.text).clone();
+        // fuzzel understands rofi's icon syntax
+        if self == Self::Fuzzel && !row.icon.is_empty() {
+            ret.push_str("\0icon\x1F");
+            ret.push_str(&row.icon);
+        }
+        ret
+    }
+
+    /// Show the menu, returns the ROFI_RETV-compatible return code, the
+    /// selected row index and the user input
+    fn show(
+        self,
+        menu: &Menu,
+        last_selection: Option<usize>,
+        args: &[String],
+    ) -> Option<(u8, Option<usize>, String)> {
+        let mut cmd = self.command(menu, last_selection);
+        cmd.args(args).stdin(Stdio::piped()).stdout(Stdio::piped());
+        let mut child = cmd.spawn().expect("failed to spawn menu backend");
+        let mut stdin = child.stdin.take().expect("menu backend is missing stdin?");
+        for row in &menu.rows {
+            stdin
+                .write_all(self.render_row(row).as_bytes())
+                .expect("failed writing into menu backend");
+            stdin
+                .write_all(b"\n")
+                .expect("failed writing into menu backend");
+        }
+        drop(stdin);
+        let mut output = String::new();
+        child
+            .stdout
+            .take()
+            .expect("menu backend is missing stdout?")
+            .read_to_string(&mut output)
+            .expect("failed reading menu backend output");
+        let status = child.wait().expect("failed waiting for menu backend");
+        let output = output.strip_suffix('\n').unwrap_or(&output);
+        let index = menu
+            .rows
+            .iter()
+            .position(|row| row.display.as_ref().unwrap_or(&row.text) == output);
+        // like rofi, report the row's text rather than what was shown
+        let input = index.map_or(output, |i| &menu.rows[i].text).to_owned();
+        let retv = match status.code() {
+            Some(0) if index.is_some() => 1,
+            Some(0) => 2,
+            // custom keybindings, as reported by e.g. fuzzel
+            Some(x @ 10..=28) => x as u8,
+            _ => return None,
+        };
+        Some((retv, index, input))
+    }
+
+    /// Drive the menu stack with this backend until the user closes the menu
+    pub fn run(self, data: Data, args: Vec<String>) {
+        let mut servers = Servers::local();
+        let mut menu = engine::step(data, Info::default(), Action::default(), &mut servers);
+        let mut last_selection = None;
+        while let Some(cur) = menu {
+            let Some((retv, index, input)) = self.show(&cur, last_selection, &args) else {
+                return;
+            };
+            if index.is_some() {
+                last_selection = index;
+            }
+            let info = match index.map(|i| &cur.rows[i]) {
+                Some(row) if row.selectable => row.info.clone(),
+                // rofi wouldn't let the user select this row
+                Some(_) => Info::default(),
+                None => cur.opts.data.fallback.clone().unwrap_or_default(),
+            };
+            let action = Action {
+                retv,
+                input,
+                row: index.is_some(),
+                status: None,
+                output: None,
+            };
+            menu = engine::step(cur.opts.data, info.for_retv(retv), action, &mut servers);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::Context;
+
+/// Guard for a row's operations, all of the given checks must pass
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// The stack has at least this many values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_len_at_least: Option<usize>,
+    /// The user input matches this regex (the whole input must match)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_matches: Option<String>,
+    /// The top of the stack is equal to this value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_equals: Option<String>,
+    /// The user input is a number in this range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_number: Option<NumberRange>,
+}
+
+/// Inclusive bounds for a number, either can be omitted
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NumberRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+impl NumberRange {
+    /// Whether `x` is a finite number in the range
+    pub fn contains(&self, x: f64) -> bool {
+        x.is_finite()
+            && self.min.map_or(true, |min| x >= min)
+            && self.max.map_or(true, |max| x <= max)
+    }
+}
+
+impl Condition {
+    fn regex(re: &str) -> Result<Regex, regex::Error> {
+        Regex::new(&format!("^(?:{re})$"))
+    }
+
+    /// Make sure the condition can be checked
+    pub fn validate(&self) -> Result<(), regex::Error> {
+        if let Some(re) = &self.input_matches {
+            Self::regex(re)?;
+        }
+        Ok(())
+    }
+
+    pub fn check(&self, ctx: &Context) -> bool {
+        if let Some(n) = self.stack_len_at_least {
+            if ctx.data.frame_stack().len() < n {
+                return false;
+            }
+        }
+        if let Some(re) = &self.input_matches {
+            if !Self::regex(re).is_ok_and(|re| re.is_match(&ctx.action.input)) {
+                return false;
+            }
+        }
+        if let Some(x) = &self.top_equals {
+            if ctx.data.frame_stack().last() != Some(x) {
+                return false;
+            }
+        }
+        if let Some(range) = &self.input_number {
+            if !ctx
+                .action
+                .input
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|x| range.contains(x))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<'a> Condition {
+    /// Deserialize and validate a condition
+    pub fn parse<A: serde::de::MapAccess<'a>>(map: &mut A) -> Result<Self, A::Error> {
+        let ret: Self = map.next_value()?;
+        ret.validate().map_err(serde::de::Error::custom)?;
+        Ok(ret)
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    frame::{Args, Frame, FrameSpec, Jump, JumpItem},
+    options::{Markup, ModeOptions},
+    row::Row,
+    Data, Info, VecString,
+};
+
+/// Call stack entry of the built-in inspector menu
+pub const SCRIPT: &str = "@debug";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Stack,
+    Frame,
+}
+
+impl Target {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Stack => "stack",
+            Self::Frame => "frame",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    /// Replace with the user input
+    Edit,
+    Duplicate,
+    Delete,
+    /// Swap with the entry above (closer to the top)
+    Up,
+    /// Swap with the entry below
+    Down,
+}
+
+/// Change to the stack or the call stack made in the inspector
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Edit {
+    pub target: Target,
+    /// Index from the bottom of the stack
+    pub index: usize,
+    pub op: Op,
+}
+
+impl Edit {
+    pub fn apply(&self, data: &mut Data, input: &str) {
+        // leave the inspector's own frames alone
+        let len = match self.target {
+            Target::Stack => data.stack.len(),
+            Target::Frame => callers(data).len(),
+        };
+        let i = self.index;
+        let valid = match self.op {
+            Op::Up => i + 1 < len,
+            Op::Down => i > 0 && i < len,
+            _ => i < len,
+        };
+        if !valid {
+            return;
+        }
+        if self.target == Target::Frame {
+            data.vars
+                .resize_with(data.call_stack.len(), Default::default);
+        }
+        match (self.target, self.op) {
+            (Target::Stack, Op::Edit) => data.stack[i] = input.to_owned(),
+            (Target::Frame, Op::Edit) => data.call_stack[i].script = input.to_owned(),
+            (Target::Stack, Op::Duplicate) => data.stack.insert(i, data.stack[i].clone()),
+            (Target::Frame, Op::Duplicate) => {
+                data.call_stack.insert(i, data.call_stack[i].clone());
+                data.vars.insert(i, data.vars[i].clone());
+            }
+            (Target::Stack, Op::Delete) => {
+                data.stack.remove(i);
+            }
+            (Target::Frame, Op::Delete) => {
+                data.call_stack.remove(i);
+                data.vars.remove(i);
+            }
+            (Target::Stack, Op::Up) => data.stack.swap(i, i + 1),
+            (Target::Frame, Op::Up) => {
+                data.call_stack.swap(i, i + 1);
+                data.vars.swap(i, i + 1);
+            }
+            (Target::Stack, Op::Down) => data.stack.swap(i - 1, i),
+            (Target::Frame, Op::Down) => {
+                data.call_stack.swap(i - 1, i);
+                data.vars.swap(i - 1, i);
+            }
+        }
+    }
+}
+
+/// Call stack below the inspector
+fn callers(data: &Data) -> &[Frame] {
+    let len = data
+        .call_stack
+        .iter()
+        .rposition(|x| x.script != SCRIPT)
+        .map_or(0, |x| x + 1);
+    &data.call_stack[..len]
+}
+
+fn describe(frame: &Frame) -> String {
+    let mut ret = frame.script.clone();
+    for arg in &frame.args {
+        ret.push(' ');
+        ret.push_str(arg);
+    }
+    ret
+}
+
+fn row(text: String, info: Info) -> Row {
+    Row {
+        text,
+        info,
+        ..Row::default()
+    }
+}
+
+fn header(text: &str) -> Row {
+    Row {
+        text: text.to_owned(),
+        selectable: false,
+        ..Row::default()
+    }
+}
+
+/// Show the inspector again with these arguments
+fn open(args: Vec<String>) -> Info {
+    Info {
+        push_call: Jump(vec![JumpItem::Frame(FrameSpec {
+            script: VecString::Single(SCRIPT.to_owned()),
+            args: Some(Args::List(
+                args.into_iter().map(VecString::Single).collect(),
+            )),
+            results: None,
+            cwd: None,
+            env: Default::default(),
+        })]),
+        ..Info::default()
+    }
+}
+
+fn back() -> Row {
+    row(
+        "Back".to_owned(),
+        Info {
+            pop_call: Some(1),
+            ..Info::default()
+        },
+    )
+}
+
+/// Apply an edit and go back to the overview
+fn edit(target: Target, index: usize, op: Op) -> Info {
+    Info {
+        pop_call: Some(1),
+        edit: Some(Edit { target, index, op }),
+        ..Info::default()
+    }
+}
+
+fn overview(data: &Data) -> (ModeOptions, Vec<Row>) {
+    let mut rows = vec![header("Stack (top first)")];
+    for (i, value) in data.stack.iter().enumerate().rev() {
+        rows.push(row(
+            format!("{}: {value}", data.stack.len() - 1 - i),
+            open(vec![Target::Stack.name().to_owned(), i.to_string()]),
+        ));
+    }
+    let callers = callers(data);
+    rows.push(header("Call stack (top first)"));
+    for (i, frame) in callers.iter().enumerate().rev() {
+        rows.push(row(
+            format!("{}: {}", callers.len() - 1 - i, describe(frame)),
+            open(vec![Target::Frame.name().to_owned(), i.to_string()]),
+        ));
+    }
+    // the inspector's own fallback is left over after editing
+    if let Some(fallback) = data.fallback.as_ref().filter(|x| x.edit.is_none()) {
+        let json = serde_json::to_string_pretty(fallback).expect("failed to serialize fallback");
+        rows.push(row(
+            "Pending fallback".to_owned(),
+            open(vec!["fallback".to_owned(), json]),
+        ));
+    }
+    rows.push(back());
+    let opts = ModeOptions {
+        prompt: Some("Debug".to_owned()),
+        ..ModeOptions::default()
+    };
+    (opts, rows)
+}
+
+fn details(target: Target, index: usize, len: usize, value: String) -> (ModeOptions, Vec<Row>) {
+    let mut rows = vec![
+        row("Duplicate".to_owned(), edit(target, index, Op::Duplicate)),
+        row("Delete".to_owned(), edit(target, index, Op::Delete)),
+    ];
+    if index + 1 < len {
+        rows.push(row("Move up".to_owned(), edit(target, index, Op::Up)));
+    }
+    if index > 0 {
+        rows.push(row("Move down".to_owned(), edit(target, index, Op::Down)));
+    }
+    rows.push(back());
+    let mut opts = ModeOptions {
+        prompt: Some(format!("Edit {}", target.name())),
+        message: Some(Markup::escape(&value)),
+        ..ModeOptions::default()
+    };
+    opts.data.fallback = Some(edit(target, index, Op::Edit));
+    (opts, rows)
+}
+
+fn gone() -> (ModeOptions, Vec<Row>) {
+    let opts = ModeOptions {
+        prompt: Some("Debug".to_owned()),
+        message: Some("This entry no longer exists".to_owned()),
+        ..ModeOptions::default()
+    };
+    (opts, vec![back()])
+}
+
+/// Render the inspector for its call stack frame
+pub fn load(frame: &Frame, data: &Data) -> (ModeOptions, Vec<Row>) {
+    let index = frame.args.get(1).and_then(|x| x.parse::<usize>().ok());
+    match (frame.args.first().map(String::as_str), index) {
+        (Some("stack"), Some(i)) => match data.stack.get(i) {
+            Some(value) => details(Target::Stack, i, data.stack.len(), value.clone()),
+            None => gone(),
+        },
+        (Some("frame"), Some(i)) => {
+            let callers = callers(data);
+            match callers.get(i) {
+                Some(frame) => details(Target::Frame, i, callers.len(), describe(frame)),
+                None => gone(),
+            }
+        }
+        (Some("fallback"), _) => {
+            let opts = ModeOptions {
+                prompt: Some("Pending fallback".to_owned()),
+                message: frame.args.get(1).map(|x| Markup::escape(x)),
+                ..ModeOptions::default()
+            };
+            (opts, vec![back()])
+        }
+        _ => overview(data),
+    }
+}
@@ -0,0 +1,605 @@
+use fork::Fork;
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::CStr,
+    io::{self, Write},
+    os::unix::process::ExitStatusExt,
+    process::{Command, Stdio},
+};
+
+use crate::{
+    debug,
+    error::Error,
+    frame::Frame,
+    frecency,
+    log::{self, Level, Stacks},
+    menu_file,
+    options::{Markup, ModeOptions, Selection, Sort},
+    page, parse_var,
+    row::Row,
+    server::{self, Request, Servers},
+    session, trace, Action, Apply, Capture, Confirm, Context, Data, Info, VecString,
+};
+
+/// A fully rendered menu, ready to be shown by a frontend
+pub struct Menu {
+    pub opts: ModeOptions,
+    pub rows: Vec<Row>,
+}
+
+impl Info {
+    /// Pick the operations to execute for the given ROFI_RETV value
+    pub fn for_retv(mut self, retv: u8) -> Self {
+        if (10..=28).contains(&retv) {
+            // an unbound key simply redraws the current menu
+            self.keys
+                .remove(&(retv - 9).to_string())
+                .unwrap_or_default()
+        } else {
+            self
+        }
+    }
+}
+
+impl Confirm {
+    /// Render the yes/no menu, "Yes" applies `info` as if the row was
+    /// selected, "No" shows the original menu again
+    fn menu(self, data: Data, info: Info, action: Action) -> Menu {
+        let no_menu = ModeOptions {
+            selection: self.row.map(|x| Selection::Set(x as i64)),
+            ..ModeOptions::default()
+        };
+        let rows = vec![
+            Row {
+                text: "Yes".to_owned(),
+                info: Info {
+                    confirm: Some(Confirm {
+                        message: self.message.clone(),
+                        row: self.row,
+                        action: Some(action.into()),
+                    }),
+                    ..info
+                },
+                ..Row::default()
+            },
+            Row {
+                text: "No".to_owned(),
+                info: Info {
+                    menu: Some(no_menu.into()),
+                    ..Info::default()
+                },
+                ..Row::default()
+            },
+        ];
+        Menu {
+            opts: ModeOptions {
+                message: Some(self.message),
+                data: Data {
+                    fallback: None,
+                    ..data
+                },
+                ..ModeOptions::default()
+            },
+            rows,
+        }
+    }
+}
+
+/// Fork off a detached process, returns `true` in the detached process
+pub fn detach() -> bool {
+    match fork::fork() {
+        Ok(Fork::Child) => {
+            let _ = fork::setsid();
+            match fork::fork() {
+                Ok(Fork::Child) => {
+                    // point stdio at /dev/null so nothing holds onto rofi's pipes
+                    let path = CStr::from_bytes_with_nul(b"/dev/null\0").unwrap();
+                    unsafe {
+                        let null = libc::open(path.as_ptr(), libc::O_RDWR);
+                        if null >= 0 {
+                            for fd in 0..3 {
+                                libc::dup2(null, fd);
+                            }
+                            if null > 2 {
+                                libc::close(null);
+                            }
+                        }
+                    }
+                    true
+                }
+                // the intermediate process must not run any cleanup code
+                _ => unsafe { libc::_exit(0) },
+            }
+        }
+        Ok(Fork::Parent(pid)) => {
+            unsafe {
+                libc::waitpid(pid, std::ptr::null_mut(), 0);
+            }
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+/// Variables as environment variables for scripts and commands
+pub fn var_env(data: &Data) -> impl Iterator<Item = (String, &str)> {
+    data.visible_vars()
+        .into_iter()
+        .map(|(k, v)| (format!("RMS_VAR_{k}"), v))
+}
+
+/// Run the command, returns its exit status (like bash's `$?`) and its
+/// output if it's to be captured
+fn exec(info: &Info, ctx: &Context) -> (i32, Option<String>) {
+    if let Some(ret) = trace::replayed_exec() {
+        return ret;
+    }
+    let mut cmd = Command::new("bash");
+    cmd.envs(var_env(ctx.data));
+    cmd.arg("-c");
+    let selected = match info.apply {
+        Some(Apply::Exec) => &ctx.data.selected[..],
+        _ => &[],
+    };
+    if matches!(info.exec, VecString::Multi(_)) {
+        cmd.arg("\"$0\" \"$@\"")
+            .args(info.exec.flatten1(ctx))
+            .args(selected);
+    } else {
+        cmd.arg(info.exec.flatten(ctx));
+        if !selected.is_empty() {
+            // the values go to "$@", so "$0" needs a value too
+            cmd.arg("rofi-menu-stack").args(selected);
+        }
+    }
+    let capture = info.capture.is_some() || info.notify.is_some();
+    if capture {
+        cmd.stdout(Stdio::piped());
+    }
+    log::command(&cmd);
+    let (status, output) = match cmd.spawn().and_then(|proc| proc.wait_with_output()) {
+        Ok(output) => (
+            output
+                .status
+                .code()
+                .or_else(|| output.status.signal().map(|x| 128 + x))
+                .unwrap_or(1),
+            capture.then(|| String::from_utf8_lossy(&output.stdout).into_owned()),
+        ),
+        Err(_) => (127, None),
+    };
+    trace::exec(status, output.as_deref());
+    (status, output)
+}
+
+/// Set up the initial call stack and value stack from the environment
+fn init(data: &mut Data) -> Result<(), Error> {
+    let script = env::var("INITIAL_SCRIPT").map_err(|_| Error::Env {
+        var: "INITIAL_SCRIPT",
+        value: None,
+        err: None,
+    })?;
+    let call_stack = parse_var(script.clone()).map_err(|err| Error::Env {
+        var: "INITIAL_SCRIPT",
+        value: Some(script),
+        err: Some(err),
+    })?;
+    if let Ok(x) = env::var("INITIAL_STACK") {
+        data.stack = parse_var(x.clone()).map_err(|err| Error::Env {
+            var: "INITIAL_STACK",
+            value: Some(x),
+            err: Some(err),
+        })?;
+    }
+    data.call_stack
+        .extend(call_stack.into_iter().map(Frame::from));
+    Ok(())
+}
+
+/// Bash command that is run the same way as the script on top of the call
+/// stack: with the script as `$0`, the frame's args and the stack as
+/// arguments, in the frame's cwd and with its env
+pub fn frame_command(cmd: &str, data: &Data) -> Command {
+    let frame = data.call_stack.last().expect("call stack is empty");
+    let mut ret = Command::new("bash");
+    ret.arg("-c")
+        .arg(cmd)
+        .arg(frame.resolve(&frame.script))
+        .args(&frame.args)
+        .args(data.frame_stack().iter().rev())
+        .env("_CALL_STACK_LEN", (data.call_stack.len() - 1).to_string())
+        .envs(var_env(data))
+        .envs(&frame.env);
+    if let Some(cwd) = &frame.cwd {
+        ret.current_dir(cwd);
+    }
+    ret
+}
+
+/// Run the script on top of the call stack and parse its output
+fn load(
+    data: &Data,
+    action: &Action,
+    servers: &mut Servers,
+) -> Result<(ModeOptions, Vec<Row>), Error> {
+    let frame = data.call_stack.last().expect("call stack is empty");
+    let argv0 = &frame.script;
+    if argv0 == debug::SCRIPT {
+        return Ok(debug::load(frame, data));
+    }
+    if menu_file::is_menu_file(argv0) {
+        return menu_file::load(frame, data.frame_stack());
+    }
+    let lines = if let Some(script) = argv0.strip_prefix(server::PREFIX) {
+        servers
+            .request(
+                script,
+                frame,
+                &Request {
+                    call_stack: &data.call_stack,
+                    stack: data.frame_stack(),
+                    input: &action.input,
+                    retv: action.retv,
+                    status: action.status,
+                    vars: data.visible_vars(),
+                },
+            )
+            .map_err(|err| Error::Spawn {
+                script: argv0.clone(),
+                err,
+            })?
+    } else {
+        let mut cmd = frame_command("\"$0\" \"$@\"", data);
+        if let Some(status) = action.status {
+            cmd.env("RMS_STATUS", status.to_string());
+        }
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        log::command(&cmd);
+        let output = cmd
+            .spawn()
+            .and_then(|child| child.wait_with_output())
+            .map_err(|err| Error::Spawn {
+                script: argv0.clone(),
+                err,
+            })?;
+        let _ = io::stderr().write_all(&output.stderr);
+        if !output.status.success() {
+            return Err(Error::Status {
+                script: argv0.clone(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    };
+    if trace::enabled() {
+        trace::output(argv0, &lines.join("\n"));
+    }
+    let mut lines = lines.into_iter();
+    let line = lines.next().unwrap_or_default();
+    log::record(Level::Debug, |x| x.options = Some(line.clone()));
+    let opts: ModeOptions = json5::from_str(&line).map_err(|err| Error::Parse {
+        script: argv0.clone(),
+        line_no: 1,
+        line,
+        err,
+    })?;
+    let mut rows = vec![];
+    for (i, line) in lines.enumerate() {
+        log::record(Level::Trace, |x| x.row_lines.push(line.clone()));
+        match json5::from_str::<Row>(&line) {
+            Ok(row) => {
+                if row.to_rofi().is_some() {
+                    rows.push(row);
+                }
+            }
+            Err(err) => {
+                return Err(Error::Parse {
+                    script: argv0.clone(),
+                    line_no: i + 2,
+                    line,
+                    err,
+                })
+            }
+        }
+    }
+    Ok((opts, rows))
+}
+
+/// Apply `info` to `data` and render the next menu (following autoselect),
+/// saving the session if there is one.
+///
+/// Returns `None` if the menu should be closed.
+pub fn step(data: Data, info: Info, action: Action, servers: &mut Servers) -> Option<Menu> {
+    log::record(Level::Debug, |x| {
+        x.retv = action.retv;
+        x.input = action.input.clone();
+        x.info = Some(info.clone());
+        x.before = Some(Stacks {
+            stack: data.stack.clone(),
+            call_stack: data.call_stack.clone(),
+        });
+    });
+    let traced = trace::enabled().then(|| (data.clone(), info.clone(), action.clone()));
+    let prev = data.clone();
+    let ret = step_inner(data, info, action, servers);
+    // replaying someone's trace shouldn't touch the user's session
+    match &ret {
+        _ if trace::replaying() => {}
+        Some(menu) => session::save(&menu.opts.data),
+        None => session::clear(&prev),
+    }
+    if let Some(menu) = &ret {
+        log::record(Level::Debug, |x| {
+            x.after = Some(Stacks {
+                stack: menu.opts.data.stack.clone(),
+                call_stack: menu.opts.data.call_stack.clone(),
+            });
+            x.rows = Some(menu.rows.len());
+        });
+    }
+    log::flush();
+    if let Some((data, info, action)) = traced {
+        trace::record(data, info, &action, ret.as_ref());
+    }
+    ret
+}
+
+fn step_inner(
+    mut data: Data,
+    mut info: Info,
+    mut action: Action,
+    servers: &mut Servers,
+) -> Option<Menu> {
+    loop {
+        if data.call_stack.is_empty() {
+            if let Err(err) = init(&mut data) {
+                log::error(&err);
+                return Some(err.menu(data));
+            }
+        }
+        if std::mem::take(&mut data.frecency)
+            && action.row
+            && !info.generated
+            && !trace::replaying()
+        {
+            if let Some(frame) = data.call_stack.last() {
+                frecency::record(&frame.script, &action.input);
+            }
+        }
+        // "Yes" in a confirmation menu stands for the original action
+        if let Some(x) = info.confirm.as_mut().and_then(|x| x.action.take()) {
+            action = *x;
+            info.confirm = None;
+        }
+        let before = data.clone();
+        let mut output = None;
+        loop {
+            let ctx = Context {
+                data: &before,
+                action: &action,
+            };
+            while info.cond.as_ref().is_some_and(|cond| !cond.check(&ctx)) {
+                info = info.otherwise.take().map_or_else(Info::default, |x| *x);
+            }
+            if let Some(confirm) = info.confirm.take() {
+                return Some(confirm.menu(data, info, action));
+            }
+            if info.exec.is_empty() {
+                if let Some(notify) = &info.notify {
+                    notify.send(&ctx);
+                }
+                break;
+            }
+            // forked commands don't affect the menu, so they're skipped when replaying
+            if info.fork {
+                if !trace::replaying() && detach() {
+                    let (status, output) = exec(&info, &ctx);
+                    if let Some(notify) = &info.notify {
+                        let action = Action {
+                            status: Some(status),
+                            output,
+                            ..action.clone()
+                        };
+                        notify.send(&Context {
+                            data: &before,
+                            action: &action,
+                        });
+                    }
+                    std::process::exit(0);
+                }
+                break;
+            }
+            let (status, out) = exec(&info, &ctx);
+            output = info.capture.zip(out.clone());
+            action.status = Some(status);
+            action.output = out;
+            if let Some(notify) = &info.notify {
+                notify.send(&Context {
+                    data: &before,
+                    action: &action,
+                });
+            }
+            // the branch may have its own guards and commands
+            let branch = if status == 0 {
+                info.on_success.take()
+            } else {
+                info.on_failure.take()
+            };
+            match branch {
+                Some(x) => info = *x,
+                None => break,
+            }
+        }
+        let ctx = Context {
+            data: &before,
+            action: &action,
+        };
+        if let Some(x) = info.pop {
+            if x <= data.frame_stack().len() {
+                data.stack.truncate(data.stack.len() - x);
+            } else {
+                return None;
+            }
+        } else {
+            // only clear the values this frame can see
+            let len = data.stack.len() - data.frame_stack().len();
+            data.stack.truncate(len);
+        }
+        for x in info.push.flatten1(&ctx) {
+            data.stack.push(x);
+        }
+        match &output {
+            Some((Capture::Push, output)) => data.stack.push(output.trim().to_owned()),
+            Some((Capture::Lines, output)) => data.stack.extend(output.lines().map(str::to_owned)),
+            _ => {}
+        }
+        if !info.toggle.is_empty() {
+            let value = info.toggle.flatten(&ctx);
+            match data.selected.iter().position(|x| *x == value) {
+                Some(i) => {
+                    data.selected.remove(i);
+                }
+                None => data.selected.push(value),
+            }
+        }
+        if let Some(apply) = info.apply {
+            let selected = std::mem::take(&mut data.selected);
+            if apply == Apply::Push {
+                data.stack.extend(selected);
+            }
+        }
+        if let Some(edit) = &info.edit {
+            edit.apply(&mut data, &action.input);
+        }
+        if let Some(x) = info.pop_call {
+            if x > data.call_stack.len() {
+                return None;
+            }
+            for _ in 0..x {
+                if !data.pop_frame() {
+                    return None;
+                }
+            }
+        } else {
+            data.call_stack.clear();
+        }
+        // returning drops the variables of the returned-from frames
+        data.vars.truncate(data.call_stack.len());
+        for frame in info.push_call.flatten(&ctx) {
+            if !data.push_frame(frame) {
+                return None;
+            }
+        }
+        if !info.set.is_empty() || !info.unset.is_empty() {
+            data.vars.resize_with(data.call_stack.len(), BTreeMap::new);
+            if let Some(vars) = data.vars.last_mut() {
+                for k in &info.unset {
+                    vars.remove(k);
+                }
+                for (k, v) in &info.set {
+                    vars.insert(k.clone(), v.flatten(&ctx));
+                }
+            }
+        }
+        data.page = match info.page {
+            Some(page) => page.apply(before.page),
+            // stay on the same page if the menu is just shown again
+            None if data.stack == before.stack && data.call_stack == before.call_stack => {
+                before.page
+            }
+            None => 0,
+        };
+        if data.call_stack.is_empty() {
+            return None;
+        }
+        let (mut opts, mut rows) = match load(&data, &action, servers) {
+            Ok(x) => x,
+            Err(err) => {
+                log::error(&err);
+                return Some(err.menu(data));
+            }
+        };
+        for row in &mut rows {
+            row.render_kind(&data);
+        }
+        opts.data = Data {
+            fallback: opts.data.fallback.take(),
+            session: opts.data.session.take().or_else(|| data.session.clone()),
+            ..data.clone()
+        };
+        if let Some(menu) = &info.menu {
+            opts.merge(menu);
+        }
+        if let Some((Capture::Message, output)) = output {
+            opts.merge(&ModeOptions {
+                message: Some(Markup::escape(output.trim_end())),
+                ..ModeOptions::default()
+            });
+        }
+        if let Some(number) = opts.number.take() {
+            number.render(&data, &mut opts, &mut rows);
+        }
+        if opts.autoselect && rows.len() == 1 {
+            let row = rows.pop().unwrap();
+            info = row.info;
+            data = opts.data;
+            action = Action {
+                retv: 1,
+                input: row.text,
+                row: true,
+                status: None,
+                output: None,
+            };
+            continue;
+        }
+        for row in &mut rows {
+            let toggle = &row.info.toggle;
+            if !toggle.is_empty() {
+                // resolve the value as if the row was selected
+                let action = Action {
+                    retv: 1,
+                    input: row.text.clone(),
+                    row: true,
+                    ..Action::default()
+                };
+                let value = toggle.flatten(&Context {
+                    data: &opts.data,
+                    action: &action,
+                });
+                if opts.data.selected.contains(&value) {
+                    row.active = true;
+                }
+            }
+        }
+        // indices in the order the script printed the rows, like the
+        // `selection` option, sorting and pagination map them to the shown
+        // rows
+        for (i, row) in rows.iter_mut().enumerate() {
+            if let Some(confirm) = &mut row.info.confirm {
+                confirm.row = Some(i);
+            }
+            // keep the toggled row selected when the menu is shown again
+            if row.kind.is_some() && row.info.menu.is_none() {
+                row.info.menu = Some(
+                    ModeOptions {
+                        selection: Some(Selection::Set(i as i64)),
+                        ..ModeOptions::default()
+                    }
+                    .into(),
+                );
+            }
+        }
+        if opts.sort == Some(Sort::Frecency) {
+            let script = &data.call_stack.last().expect("call stack is empty").script;
+            frecency::sort(script, &mut opts, &mut rows);
+            opts.data.frecency = true;
+        }
+        page::paginate(&mut opts, &mut rows);
+        return Some(Menu { opts, rows });
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fmt, io, process::ExitStatus};
+
+use crate::{
+    engine::Menu,
+    options::{Markup, ModeOptions},
+    row::Row,
+    Data, Info,
+};
+
+/// Why a menu couldn't be shown
+#[derive(Debug)]
+pub enum Error {
+    /// INITIAL_SCRIPT or INITIAL_STACK is missing or invalid
+    Env {
+        var: &'static str,
+        value: Option<String>,
+        err: Option<json5::Error>,
+    },
+    /// The script couldn't be started or talked to
+    Spawn { script: String, err: io::Error },
+    /// The script exited with a non-zero status
+    Status {
+        script: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// The script printed invalid options or rows
+    Parse {
+        script: String,
+        /// 1-based line number in the script output
+        line_no: usize,
+        line: String,
+        err: json5::Error,
+    },
+    /// The menu file couldn't be read or is invalid
+    File { script: String, err: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env { var, value, err } => {
+                match value {
+                    None => write!(f, "{var} must be set")?,
+                    Some(value) => write!(f, "{var} is invalid: {value}")?,
+                }
+                if let Some(err) = err {
+                    write!(f, "\n{err}")?;
+                }
+                Ok(())
+            }
+            Self::Spawn { script, err } => write!(f, "{script}: failed to run script: {err}"),
+            Self::Status {
+                script,
+                status,
+                stderr,
+            } => {
+                write!(f, "{script}: {status}")?;
+                if !stderr.is_empty() {
+                    write!(f, "\n{}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+            Self::Parse {
+                script,
+                line_no,
+                line,
+                err,
+            } => {
+                let what = if *line_no == 1 { "menu options" } else { "row" };
+                write!(f, "{script}: invalid {what} on line {line_no}")?;
+                if let json5::Error::Message {
+                    location: Some(loc),
+                    ..
+                } = err
+                {
+                    write!(f, ", column {}", loc.column)?;
+                }
+                write!(f, "\n{}\n{err}", line.trim_end())
+            }
+            Self::File { script, err } => write!(f, "{script}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// Render the error as a menu that lets the user go back, retry or close
+    pub fn menu(&self, data: Data) -> Menu {
+        let mut rows = vec![];
+        // popping the last script would close the menu anyway
+        if data.call_stack.len() > 1 {
+            rows.push(Row {
+                text: "Back".to_owned(),
+                info: Info {
+                    pop_call: Some(1),
+                    ..Info::default()
+                },
+                ..Row::default()
+            });
+        }
+        rows.push(Row {
+            text: "Retry".to_owned(),
+            ..Row::default()
+        });
+        rows.push(Row {
+            text: "Close".to_owned(),
+            info: Info {
+                pop_call: None,
+                ..Info::default()
+            },
+            ..Row::default()
+        });
+        Menu {
+            opts: ModeOptions {
+                prompt: Some("Error".to_owned()),
+                message: Some(Markup::escape(&self.to_string())),
+                data: Data {
+                    fallback: None,
+                    ..data
+                },
+                ..ModeOptions::default()
+            },
+            rows,
+        }
+    }
+}
@@ -1,13 +1,35 @@
+use std::collections::BTreeMap;
+
 use serde::{de::Visitor, Deserialize};
 
-use crate::{Info, ModeOptions};
+use crate::{condition::Condition, Confirm, Info, ModeOptions};
 
 #[derive(Clone, Default)]
 pub struct FallbackRow(pub Info);
 
 impl FallbackRow {
     const FIELDS: &[&'static str] = &[
-        "push", "pop", "jump", "goto", "return", "exec", "fork", "menu",
+        "push",
+        "pop",
+        "jump",
+        "goto",
+        "return",
+        "exec",
+        "fork",
+        "capture",
+        "on_success",
+        "on_failure",
+        "notify",
+        "confirm",
+        "toggle",
+        "apply",
+        "page",
+        "menu",
+        "keys",
+        "set",
+        "unset",
+        "if",
+        "else",
     ];
 }
 
@@ -33,7 +55,30 @@ impl<'a> Visitor<'a> for RowVisitor {
                 "return" => ret.0.pop_call = map.next_value()?,
                 "exec" => ret.0.exec = map.next_value()?,
                 "fork" => ret.0.fork = map.next_value()?,
+                "capture" => ret.0.capture = Some(map.next_value()?),
+                "notify" => ret.0.notify = Some(map.next_value()?),
+                "toggle" => ret.0.toggle = map.next_value()?,
+                "apply" => ret.0.apply = Some(map.next_value()?),
+                "page" => ret.0.page = Some(map.next_value()?),
+                "confirm" => {
+                    ret.0.confirm = Some(Confirm {
+                        message: map.next_value()?,
+                        row: None,
+                        action: None,
+                    });
+                }
+                "on_success" => {
+                    ret.0.on_success = Some(map.next_value::<FallbackRow>()?.0.into());
+                }
+                "on_failure" => {
+                    ret.0.on_failure = Some(map.next_value::<FallbackRow>()?.0.into());
+                }
                 "menu" => ret.0.menu = Some(map.next_value::<ModeOptions>()?.into()),
+                "keys" => ret.0.keys = map.next_value::<Keys>()?.0,
+                "set" => ret.0.set = map.next_value()?,
+                "unset" => ret.0.unset = map.next_value()?,
+                "if" => ret.0.cond = Some(Condition::parse(&mut map)?),
+                "else" => ret.0.otherwise = Some(map.next_value::<FallbackRow>()?.0.into()),
                 key => return Err(serde::de::Error::unknown_field(key, Self::Value::FIELDS)),
             }
         }
@@ -46,3 +91,28 @@ impl<'a> Deserialize<'a> for FallbackRow {
         d.deserialize_any(RowVisitor)
     }
 }
+
+/// Per-keybinding operations, keyed by custom keybinding number (1-19)
+#[derive(Clone, Default)]
+pub struct Keys(pub BTreeMap<String, Info>);
+
+impl<'a> Deserialize<'a> for Keys {
+    fn deserialize<D: serde::Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        let map = BTreeMap::<String, FallbackRow>::deserialize(d)?;
+        let mut ret = BTreeMap::new();
+        for (key, row) in map {
+            match key.parse::<u8>() {
+                Ok(n @ 1..=19) => {
+                    ret.insert(n.to_string(), row.0);
+                }
+                _ => {
+                    return Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(&key),
+                        &"a custom keybinding number (1-19)",
+                    ))
+                }
+            }
+        }
+        Ok(Self(ret))
+    }
+}
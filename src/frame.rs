@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, path::Path};
+
+use crate::{Context, VecString};
+
+/// A call stack entry: the script to run for the menu, and how to run it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub script: String,
+    /// Arguments passed to the script before the stack values
+    pub args: Vec<String>,
+    /// How many values of the caller's stack this frame can see
+    pub arity: Option<usize>,
+    /// How many values to return to the caller
+    pub results: Option<usize>,
+    /// Stack length below this frame's values, set for frames with an arity
+    pub base: Option<usize>,
+    /// Working directory of the script
+    pub cwd: Option<String>,
+    /// Extra environment variables for the script
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrameFields {
+    script: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    arity: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    results: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+}
+
+/// Frames that are just a script are stored as a plain string
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FrameRepr {
+    Script(String),
+    Fields(FrameFields),
+}
+
+impl Frame {
+    /// Path to run `script` by: relative paths are resolved against the
+    /// directory rofi-menu-stack runs in rather than the frame's `cwd`
+    pub fn resolve(&self, script: &str) -> String {
+        if self.cwd.is_none() || !script.contains('/') || Path::new(script).is_absolute() {
+            return script.to_owned();
+        }
+        env::current_dir().map_or_else(
+            |_| script.to_owned(),
+            |dir| dir.join(script).to_string_lossy().into_owned(),
+        )
+    }
+}
+
+impl From<String> for Frame {
+    fn from(script: String) -> Self {
+        Self {
+            script,
+            ..Self::default()
+        }
+    }
+}
+
+impl Serialize for Frame {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.args.is_empty()
+            && self.arity.is_none()
+            && self.results.is_none()
+            && self.cwd.is_none()
+            && self.env.is_empty()
+        {
+            return serializer.serialize_str(&self.script);
+        }
+        FrameRepr::Fields(FrameFields {
+            script: self.script.clone(),
+            args: self.args.clone(),
+            arity: self.arity,
+            results: self.results,
+            base: self.base,
+            cwd: self.cwd.clone(),
+            env: self.env.clone(),
+        })
+        .serialize(serializer)
+    }
+}
+
+impl<'a> Deserialize<'a> for Frame {
+    fn deserialize<D: serde::Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        Ok(match FrameRepr::deserialize(d)? {
+            FrameRepr::Script(script) => script.into(),
+            FrameRepr::Fields(x) => Self {
+                script: x.script,
+                args: x.args,
+                arity: x.arity,
+                results: x.results,
+                base: x.base,
+                cwd: x.cwd,
+                env: x.env,
+            },
+        })
+    }
+}
+
+/// Either the arguments to pass to the script, or how many stack values
+/// it can see
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Args {
+    Arity(usize),
+    List(Vec<VecString>),
+}
+
+/// A frame to push onto the call stack, with placeholders
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrameSpec {
+    pub script: VecString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Args>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<VecString>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, VecString>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JumpItem {
+    Frame(FrameSpec),
+    Script(VecString),
+}
+
+/// Frames to push onto the call stack (`jump`)
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct Jump(pub Vec<JumpItem>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JumpRepr {
+    Many(Vec<JumpItem>),
+    One(JumpItem),
+}
+
+impl<'a> Deserialize<'a> for Jump {
+    fn deserialize<D: serde::Deserializer<'a>>(d: D) -> Result<Self, D::Error> {
+        Ok(match JumpRepr::deserialize(d)? {
+            JumpRepr::Many(x) => Self(x),
+            JumpRepr::One(x) => Self(vec![x]),
+        })
+    }
+}
+
+impl Jump {
+    pub fn flatten(&self, ctx: &Context) -> Vec<Frame> {
+        self.0
+            .iter()
+            .map(|item| match item {
+                JumpItem::Script(x) => x.flatten(ctx).into(),
+                JumpItem::Frame(x) => Frame {
+                    script: x.script.flatten(ctx),
+                    args: match &x.args {
+                        Some(Args::List(args)) => args.iter().map(|x| x.flatten(ctx)).collect(),
+                        _ => vec![],
+                    },
+                    arity: match x.args {
+                        Some(Args::Arity(n)) => Some(n),
+                        _ => None,
+                    },
+                    results: x.results,
+                    base: None,
+                    cwd: x.cwd.as_ref().map(|x| x.flatten(ctx)),
+                    env: x
+                        .env
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.flatten(ctx)))
+                        .collect(),
+                },
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    log,
+    options::{ModeOptions, Selection},
+    row::Row,
+    session, trace,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Entry {
+    count: u64,
+    /// Unix timestamp of the last selection
+    last: u64,
+}
+
+impl Entry {
+    fn score(&self, now: u64) -> u64 {
+        // the same buckets as Firefox's frecency
+        let weight = match now.saturating_sub(self.last) / DAY {
+            0..=3 => 100,
+            4..=13 => 70,
+            14..=30 => 50,
+            31..=89 => 30,
+            _ => 10,
+        };
+        self.count * weight
+    }
+}
+
+/// Selection history, by script and row text
+type History = BTreeMap<String, BTreeMap<String, Entry>>;
+
+fn path() -> PathBuf {
+    session::state_dir().join("frecency.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+fn load() -> History {
+    fs::read(path())
+        .ok()
+        .and_then(|x| serde_json::from_slice(&x).ok())
+        .unwrap_or_default()
+}
+
+/// Remember that a row was selected in a script's menu
+pub fn record(script: &str, text: &str) {
+    let mut history = load();
+    let entry = history
+        .entry(script.to_owned())
+        .or_default()
+        .entry(text.to_owned())
+        .or_default();
+    entry.count += 1;
+    entry.last = now();
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let json = serde_json::to_vec(&history).expect("failed to serialize frecency history");
+    if let Err(err) = fs::write(&path, json) {
+        log::error(format!(
+            "failed to save frecency history {}: {err}",
+            path.display()
+        ));
+    }
+}
+
+/// Indices of the rows, most frequently and recently selected first
+fn order(script: &str, rows: &[Row]) -> Vec<usize> {
+    let mut ret: Vec<usize> = (0..rows.len()).collect();
+    let history = load();
+    if let Some(entries) = history.get(script) {
+        let now = now();
+        ret.sort_by_key(|i| {
+            std::cmp::Reverse(entries.get(&rows[*i].text).map_or(0, |x| x.score(now)))
+        });
+    }
+    ret
+}
+
+/// Put the most frequently and recently selected rows first, keeping the
+/// row indices in the options pointing at the same rows
+pub fn sort(script: &str, opts: &mut ModeOptions, rows: &mut Vec<Row>) {
+    let mut order = trace::replayed_sort().unwrap_or_else(|| order(script, rows));
+    trace::sort(&order);
+    // a replayed order may not fit if the script prints other rows now
+    let mut seen = vec![false; rows.len()];
+    order.retain(|i| {
+        seen.get_mut(*i)
+            .is_some_and(|x| !std::mem::replace(x, true))
+    });
+    order.extend((0..rows.len()).filter(|i| !seen[*i]));
+    let mut old: Vec<Option<Row>> = rows.drain(..).map(Some).collect();
+    let mut new_index = vec![0; old.len()];
+    for (i, x) in order.into_iter().enumerate() {
+        new_index[x] = i;
+        rows.extend(old[x].take());
+    }
+    for x in opts
+        .urgent
+        .iter_mut()
+        .chain(opts.active.iter_mut())
+        .flatten()
+    {
+        if let Some(i) = new_index.get(*x) {
+            *x = *i;
+        }
+    }
+    if let Some(Selection::Set(x)) = &mut opts.selection {
+        if let Some(i) = usize::try_from(*x).ok().and_then(|x| new_index.get(x)) {
+            *x = *i as i64;
+        }
+    }
+}
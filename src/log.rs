@@ -0,0 +1,132 @@
+use serde::Serialize;
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, Write},
+    process::Command,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{frame::Frame, Info};
+
+/// How much to log, set with `RMS_LOG`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Level {
+    /// One record per step
+    Debug,
+    /// Also every line the scripts print
+    Trace,
+}
+
+fn level() -> Option<Level> {
+    static LEVEL: OnceLock<Option<Level>> = OnceLock::new();
+    *LEVEL.get_or_init(|| match env::var("RMS_LOG").ok()?.as_str() {
+        "debug" => Some(Level::Debug),
+        "trace" => Some(Level::Trace),
+        x => {
+            eprintln!("unknown RMS_LOG level {x:?}, expected debug or trace");
+            None
+        }
+    })
+}
+
+/// What rofi passed to this process
+#[derive(Debug, Default, Serialize)]
+pub struct Rofi {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+/// Stacks at the start or the end of a step
+#[derive(Debug, Serialize)]
+pub struct Stacks {
+    pub stack: Vec<String>,
+    pub call_stack: Vec<Frame>,
+}
+
+/// Everything that happened during one step
+#[derive(Debug, Default, Serialize)]
+pub struct Step {
+    /// Unix timestamp in milliseconds
+    pub time: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rofi: Option<Rofi>,
+    pub retv: u8,
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<Info>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Stacks>,
+    /// `None` if the menu was closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Stacks>,
+    /// Scripts and commands that were run, as argv
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<Vec<String>>,
+    /// Menu options line printed by the script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<String>,
+    /// Row lines printed by the script (trace only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub row_lines: Vec<String>,
+    /// Number of rows shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+static STEP: Mutex<Option<Step>> = Mutex::new(None);
+
+/// Add to the record of the current step if logging at this level
+pub fn record(level: Level, f: impl FnOnce(&mut Step)) {
+    if self::level().is_some_and(|x| x >= level) {
+        let mut step = STEP.lock().unwrap_or_else(|x| x.into_inner());
+        f(step.get_or_insert_with(Step::default));
+    }
+}
+
+/// Record a command that is about to be spawned
+pub fn command(cmd: &Command) {
+    record(Level::Debug, |x| {
+        x.commands.push(
+            std::iter::once(cmd.get_program())
+                .chain(cmd.get_args())
+                .map(|x| x.to_string_lossy().into_owned())
+                .collect(),
+        );
+    });
+}
+
+/// Record an error
+pub fn error(err: impl ToString) {
+    record(Level::Debug, |x| x.errors.push(err.to_string()));
+}
+
+/// Write the record of the current step to `RMS_LOG_FILE` (or stderr)
+pub fn flush() {
+    let Some(mut step) = STEP.lock().unwrap_or_else(|x| x.into_inner()).take() else {
+        return;
+    };
+    step.time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_millis());
+    let mut line = serde_json::to_vec(&step).expect("failed to serialize log record");
+    line.push(b'\n');
+    let res = match env::var_os("RMS_LOG_FILE") {
+        Some(path) => OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&line)),
+        None => io::stderr().write_all(&line),
+    };
+    if let Err(err) = res {
+        eprintln!("failed to write log: {err}");
+    }
+}
@@ -1,17 +1,41 @@
-use fork::Fork;
-use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Serialize};
+use serde::{
+    de::Visitor,
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Serialize,
+};
 use std::{
+    collections::BTreeMap,
     env,
-    io::{stdout, BufRead, BufReader, Write},
-    process::{Command, Stdio},
+    io::{stdout, Write},
 };
 
+mod backend;
+mod condition;
+mod debug;
+mod engine;
+mod error;
 mod fallback_row;
+mod frame;
+mod frecency;
+mod log;
+mod menu_file;
+mod notify;
 mod options;
+mod page;
 mod row;
+mod server;
+mod session;
+mod simulate;
+mod trace;
+mod widget;
 
+use backend::Backend;
+use condition::Condition;
+use frame::{Frame, Jump};
+use notify::Notify;
 use options::ModeOptions;
-use row::Row;
+use page::Page;
+use server::Servers;
 
 const DELIM: char = '\x0b';
 
@@ -20,24 +44,91 @@ pub enum VecString {
     Multi(Vec<VecString>),
     Single(String),
     UserInput,
+    /// Value N deep in the stack (0 is the top)
+    Stack(usize),
+    /// Environment variable
+    Env(String),
+    /// Named variable
+    Var(String),
+    /// Selected row's text
+    Text,
+    /// ROFI_RETV
+    Retv,
+    /// Exit status of the last `exec`
+    Status,
+    /// Output of the last `exec`
+    Output,
+}
+
+/// What placeholders in a [`VecString`] are resolved against
+pub struct Context<'a> {
+    /// Data before the operations are applied
+    pub data: &'a Data,
+    pub action: &'a Action,
+}
+
+impl Default for VecString {
+    fn default() -> Self {
+        Self::Multi(vec![])
+    }
 }
 
 impl VecString {
+    const PLACEHOLDERS: &'static [&'static str] =
+        &["stack", "env", "var", "text", "retv", "status", "output"];
+
     fn is_empty(&self) -> bool {
         matches!(self, Self::Multi(x) if x.is_empty())
     }
-    fn flatten(&self, input: &str) -> String {
+    /// Replace user input placeholders with a fixed value
+    fn with_input(&self, input: &str) -> Self {
+        match self {
+            Self::Multi(v) => Self::Multi(v.iter().map(|x| x.with_input(input)).collect()),
+            Self::UserInput => Self::Single(input.to_owned()),
+            x => x.clone(),
+        }
+    }
+    fn flatten(&self, ctx: &Context) -> String {
         match self {
-            Self::Multi(v) => v.iter().map(|x| x.flatten(input)).collect::<String>(),
+            Self::Multi(v) => v.iter().map(|x| x.flatten(ctx)).collect::<String>(),
             Self::Single(s) => s.clone(),
-            Self::UserInput => input.to_owned(),
+            Self::UserInput => ctx.action.input.clone(),
+            Self::Stack(i) => ctx
+                .data
+                .frame_stack()
+                .iter()
+                .rev()
+                .nth(*i)
+                .cloned()
+                .unwrap_or_default(),
+            Self::Env(name) => env::var(name).unwrap_or_default(),
+            Self::Var(name) => ctx
+                .data
+                .visible_vars()
+                .get(name.as_str())
+                .map(|x| (*x).to_owned())
+                .unwrap_or_default(),
+            Self::Text => {
+                if ctx.action.row {
+                    ctx.action.input.clone()
+                } else {
+                    String::new()
+                }
+            }
+            Self::Retv => ctx.action.retv.to_string(),
+            Self::Status => ctx.action.status.map(|x| x.to_string()).unwrap_or_default(),
+            Self::Output => ctx
+                .action
+                .output
+                .as_deref()
+                .map(|x| x.trim_end().to_owned())
+                .unwrap_or_default(),
         }
     }
-    fn flatten1(&self, input: &str) -> Vec<String> {
+    fn flatten1(&self, ctx: &Context) -> Vec<String> {
         match self {
-            Self::Multi(v) => v.iter().map(|x| x.flatten(input)).collect(),
-            Self::Single(s) => vec![s.clone()],
-            Self::UserInput => vec![input.to_owned()],
+            Self::Multi(v) => v.iter().map(|x| x.flatten(ctx)).collect(),
+            x => vec![x.flatten(ctx)],
         }
     }
 }
@@ -57,6 +148,41 @@ impl Serialize for VecString {
                 }
                 seq.end()
             }
+            Self::Stack(i) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("stack", i)?;
+                map.end()
+            }
+            Self::Env(name) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("env", name)?;
+                map.end()
+            }
+            Self::Var(name) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("var", name)?;
+                map.end()
+            }
+            Self::Text => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("text", &true)?;
+                map.end()
+            }
+            Self::Retv => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("retv", &true)?;
+                map.end()
+            }
+            Self::Status => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("status", &true)?;
+                map.end()
+            }
+            Self::Output => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("output", &true)?;
+                map.end()
+            }
         }
     }
 }
@@ -65,7 +191,7 @@ struct VecStringVisitor;
 impl<'a> Visitor<'a> for VecStringVisitor {
     type Value = VecString;
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("a list of strings")
+        formatter.write_str("a list of strings or placeholders")
     }
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
@@ -107,6 +233,45 @@ impl<'a> Visitor<'a> for VecStringVisitor {
         }
         Ok(VecString::Multi(ret))
     }
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'a>,
+    {
+        let Some(key) = map.next_key::<String>()? else {
+            return Err(serde::de::Error::invalid_length(0, &"a single placeholder"));
+        };
+        let ret = match key.as_str() {
+            "stack" => VecString::Stack(map.next_value()?),
+            "env" => VecString::Env(map.next_value()?),
+            "var" => VecString::Var(map.next_value()?),
+            "text" => {
+                map.next_value::<bool>()?;
+                VecString::Text
+            }
+            "retv" => {
+                map.next_value::<bool>()?;
+                VecString::Retv
+            }
+            "status" => {
+                map.next_value::<bool>()?;
+                VecString::Status
+            }
+            "output" => {
+                map.next_value::<bool>()?;
+                VecString::Output
+            }
+            key => {
+                return Err(serde::de::Error::unknown_field(
+                    key,
+                    VecString::PLACEHOLDERS,
+                ))
+            }
+        };
+        if map.next_key::<String>()?.is_some() {
+            return Err(serde::de::Error::invalid_length(2, &"a single placeholder"));
+        }
+        Ok(ret)
+    }
 }
 impl<'de> Deserialize<'de> for VecString {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -117,34 +282,218 @@ impl<'de> Deserialize<'de> for VecString {
     }
 }
 
+/// What the user did to get to the next menu
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Action {
+    /// ROFI_RETV
+    pub retv: u8,
+    /// Selected row's text or custom user input
+    pub input: String,
+    /// Whether a row (rather than custom input) was selected
+    pub row: bool,
+    /// Exit status of the last `exec`, if it was waited for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+    /// Output of the last `exec`, if it was captured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Data {
     pub stack: Vec<String>,
-    pub call_stack: Vec<String>,
+    pub call_stack: Vec<Frame>,
     pub fallback: Option<Info>,
+    /// Named variables for each call stack frame (may be shorter than the call stack)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<BTreeMap<String, String>>,
+    /// Name of the session to save this data to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Values selected with `toggle`, in the order they were selected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selected: Vec<String>,
+    /// Whether the menu is sorted by frecency, so the selection should be recorded
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub frecency: bool,
+    /// Current page of a paginated menu
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub page: usize,
+}
+
+fn is_zero(x: &usize) -> bool {
+    *x == 0
+}
+
+impl Data {
+    /// Part of the stack visible to the top call stack frame
+    pub fn frame_stack(&self) -> &[String] {
+        let base = self
+            .call_stack
+            .iter()
+            .rev()
+            .find_map(|x| x.base)
+            .unwrap_or(0);
+        &self.stack[base.min(self.stack.len())..]
+    }
+
+    /// Push a frame onto the call stack, returns `false` if there are
+    /// fewer values on the stack than its arity
+    pub fn push_frame(&mut self, mut frame: Frame) -> bool {
+        if let Some(n) = frame.arity {
+            if n > self.frame_stack().len() {
+                return false;
+            }
+            frame.base = Some(self.stack.len() - n);
+        }
+        self.call_stack.push(frame);
+        true
+    }
+
+    /// Pop a frame from the call stack, restoring the caller's stack and
+    /// pushing the frame's results onto it. Returns `false` if there are
+    /// fewer values on the stack than the frame's results
+    pub fn pop_frame(&mut self) -> bool {
+        let Some(frame) = self.call_stack.pop() else {
+            return false;
+        };
+        if let Some(base) = frame.base {
+            let results = frame.results.unwrap_or(0);
+            if self.stack.len() < base + results {
+                return false;
+            }
+            self.stack.drain(base..self.stack.len() - results);
+        }
+        true
+    }
+
+    /// Variables visible to the top call stack frame (callers' variables included)
+    pub fn visible_vars(&self) -> BTreeMap<&str, &str> {
+        self.vars
+            .iter()
+            .take(self.call_stack.len())
+            .flatten()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+}
+
+/// What to do with the output of `exec`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capture {
+    /// Push the trimmed output onto the stack
+    Push,
+    /// Push every line of the output onto the stack
+    Lines,
+    /// Show the output as the next menu's message
+    Message,
+}
+
+/// What to do with the selected values of a multi-select menu
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Apply {
+    /// Push the values onto the stack
+    Push,
+    /// Pass the values to `exec` as arguments
+    Exec,
+}
+
+/// Confirmation to ask for before applying a row's operations
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Confirm {
+    pub message: String,
+    /// Index of the row in its menu, to select it again if the user says no
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    /// The action that led to the confirmation, set once the user says yes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<Box<Action>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Info {
-    pub push_call: VecString,
+    pub push_call: Jump,
     pub push: VecString,
     pub pop_call: Option<usize>,
     pub pop: Option<usize>,
     pub exec: VecString,
     pub fork: bool,
+    /// What to do with the output of `exec` (ignored if `fork` is set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture: Option<Capture>,
+    /// Operations to apply instead if `exec` succeeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_success: Option<Box<Info>>,
+    /// Operations to apply instead if `exec` fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<Box<Info>>,
+    /// Notification to show once `exec` completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<Notify>,
+    /// Ask for confirmation before applying these operations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm: Option<Confirm>,
+    /// Add this value to the selected values, or remove it if it's there
+    #[serde(default, skip_serializing_if = "VecString::is_empty")]
+    pub toggle: VecString,
+    /// Use the selected values and clear them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apply: Option<Apply>,
+    /// Switch to another page of a paginated menu
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<Page>,
+    /// Change made in the built-in inspector menu (not available to scripts)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit: Option<debug::Edit>,
+    /// Row added by the engine rather than the script (not available to
+    /// scripts), its selections aren't recorded for frecency
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
     pub menu: Option<Box<ModeOptions>>,
+    /// Alternate operations for custom keybindings (kb-custom-1 to kb-custom-19)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, Info>,
+    /// Variables to set in the top call stack frame
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, VecString>,
+    /// Variables to remove from the top call stack frame
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unset: Vec<String>,
+    /// Only apply these operations if this condition holds
+    #[serde(rename = "if", default, skip_serializing_if = "Option::is_none")]
+    pub cond: Option<Condition>,
+    /// Operations to apply instead if the condition doesn't hold
+    #[serde(rename = "else", default, skip_serializing_if = "Option::is_none")]
+    pub otherwise: Option<Box<Info>>,
 }
 
 impl Default for Info {
     fn default() -> Self {
         Self {
             push: VecString::Multi(vec![]),
-            push_call: VecString::Multi(vec![]),
+            push_call: Jump::default(),
             pop: Some(0),
             pop_call: Some(0),
             exec: VecString::Multi(vec![]),
             fork: false,
+            capture: None,
+            on_success: None,
+            on_failure: None,
+            notify: None,
+            confirm: None,
+            toggle: VecString::default(),
+            apply: None,
+            page: None,
+            edit: None,
+            generated: false,
             menu: None,
+            keys: BTreeMap::new(),
+            set: BTreeMap::new(),
+            unset: vec![],
+            cond: None,
+            otherwise: None,
         }
     }
 }
@@ -158,31 +507,44 @@ fn parse_var(var: String) -> Result<Vec<String>, json5::Error> {
     }
 }
 
+/// Session name passed to `--resume`
+fn resume_name(name: Option<String>) -> String {
+    let name = name.expect("provide a session name to resume");
+    assert!(session::valid_name(&name), "invalid session name {name:?}");
+    name
+}
+
 fn main() {
     // 0: init
     // 1: selected entry
     // 2: selected custom entry
     // 10-28: custom keybindings 1-19
-    // let retv = env::var("ROFI_RETV").ok();
+    let retv = env::var("ROFI_RETV")
+        .ok()
+        .and_then(|x| x.parse::<u8>().ok())
+        .unwrap_or_default();
     // common info
     let data = env::var("ROFI_DATA").ok();
     // row info
     let info = env::var("ROFI_INFO").ok();
+    let mut args = env::args().skip(1).peekable();
+    let mut resume = None;
+    if args.peek().map(String::as_str) == Some("--resume") {
+        args.next();
+        resume = Some(resume_name(args.next()));
+    }
     // row text
-    let input = env::args().nth(1);
+    let input = args.next();
     let first_launch = info.is_none() && data.is_none();
     let mut out = stdout().lock();
     if first_launch {
         if let Some(input) = input {
-            #[allow(clippy::single_match)]
             match input.as_str() {
                 "unjson5" => {
                     out.write_all(
                         &serde_json::to_vec(
                             &json5::from_str::<serde_json::Value>(
-                                &env::args()
-                                    .nth(2)
-                                    .expect("provide json5 to convert to json"),
+                                &args.next().expect("provide json5 to convert to json"),
                             )
                             .expect("invalid json5"),
                         )
@@ -190,6 +552,43 @@ fn main() {
                     )
                     .expect("failed writing into stdout");
                 }
+                "run" => {
+                    let mut backend = Backend::Dmenu;
+                    while let Some(arg) = args.next() {
+                        match arg.as_str() {
+                            "--backend" => {
+                                let name = args.next().expect("provide a backend name");
+                                backend = Backend::from_name(&name).unwrap_or_else(|| {
+                                    panic!(
+                                        "unknown backend {name:?}, expected one of {:?}",
+                                        Backend::ITEMS
+                                    )
+                                });
+                            }
+                            "--resume" => {
+                                resume = Some(resume_name(args.next()));
+                            }
+                            "--" => break,
+                            arg => panic!("unknown argument {arg:?}"),
+                        }
+                    }
+                    let data = resume.as_deref().map(session::load).unwrap_or_default();
+                    backend.run(data, args.collect());
+                }
+                "simulate" => {
+                    let data = resume.as_deref().map(session::load).unwrap_or_default();
+                    if let Err(err) = simulate::run(data, &args.collect::<Vec<_>>()) {
+                        eprintln!("{err}");
+                        std::process::exit(1);
+                    }
+                }
+                "replay" => {
+                    let path = args.next().expect("provide a trace file to replay");
+                    if let Err(err) = trace::replay(&path) {
+                        eprintln!("{err}");
+                        std::process::exit(1);
+                    }
+                }
                 _ => {}
             }
             return;
@@ -201,160 +600,42 @@ fn main() {
             out.write_all(b"\n").expect("failed writing into stdout");
         }
     }
-    let enable_debug = cfg!(debug_assertions);
-    if enable_debug {
-        eprintln!("data {data:?}, info {info:?}");
-    }
-    let mut data: Data = json5::from_str(&data.unwrap_or_default()).unwrap_or_default();
-    let mut info: Info = info.as_deref().map_or_else(
-        || data.fallback.clone().unwrap_or_default(),
-        |info| json5::from_str(info).expect("failed to parse info"),
-    );
-    let mut input = input.as_deref().unwrap_or_default().to_owned();
-    loop {
-        if !info.exec.is_empty()
-            && (!info.fork
-                || (matches!(fork::daemon(true, true), Ok(Fork::Child)) && {
-                    let _ = fork::close_fd();
-                    true
-                }))
-        {
-            let mut cmd = Command::new("bash");
-            cmd.arg("-c");
-            if matches!(info.exec, VecString::Multi(_)) {
-                cmd.arg("\"$0\" \"$@\"").args(info.exec.flatten1(&input));
-            } else {
-                cmd.arg(info.exec.flatten(&input));
-            }
-            if let Ok(mut proc) = cmd.spawn() {
-                let _ = proc.wait();
-            }
-            if info.fork {
-                return;
-            }
-        }
-        if data.call_stack.is_empty() {
-            if enable_debug {
-                eprintln!("pushing initial_script");
-            }
-            data.call_stack.extend(
-                parse_var(
-                    env::var("INITIAL_SCRIPT")
-                        .expect("INITIAL_SCRIPT must be set as the default submenu to call"),
-                )
-                .expect("INITIAL_SCRIPT must be valid json5"),
-            );
-            if let Ok(x) = env::var("INITIAL_STACK") {
-                data.stack = parse_var(x).expect("INITIAL_STACK must be valid json5");
-            }
-        }
-        if let Some(x) = info.pop {
-            if x <= data.stack.len() {
-                data.stack.truncate(data.stack.len() - x);
-            } else {
-                return;
-            }
-        } else {
-            data.stack.clear();
-        }
-        for x in info.push.flatten1(&input) {
-            data.stack.push(x);
-        }
-        if let Some(x) = info.pop_call {
-            if x <= data.call_stack.len() {
-                data.call_stack.truncate(data.call_stack.len() - x);
-            } else {
-                return;
-            }
-        } else {
-            data.call_stack.clear();
-        }
-        for x in info.push_call.flatten1(&input) {
-            data.call_stack.push(x);
-        }
-        if enable_debug {
-            eprintln!("data {data:?}, info {info:?}");
-        }
-        let Some(argv0) = data.call_stack.last() else {
-            return;
-        };
-        let mut cmd = Command::new("bash");
-        cmd.arg("-c")
-            .arg("\"$0\" \"$@\"")
-            .arg(argv0)
-            .env("_CALL_STACK_LEN", (data.call_stack.len() - 1).to_string());
-        if enable_debug {
-            data.stack.reverse();
-            eprintln!("passing args {:?}", data.stack);
-            data.stack.reverse();
-        }
-        cmd.args(data.stack.iter().rev());
-        cmd.stdout(Stdio::piped());
-        let cmd = cmd.spawn().expect("failed to spawn script");
-        let mut buf = BufReader::new(cmd.stdout.expect("script is missing stdout?"));
-        let mut line = String::new();
-        buf.read_line(&mut line)
-            .expect("failed to read menu options");
-        if enable_debug {
-            eprintln!("opts: {line:?}");
-        }
-        let mut opts: ModeOptions = json5::from_str(&line).expect("failed to parse menu options");
-        opts.data.call_stack = data.call_stack.clone();
-        opts.data.stack = data.stack.clone();
-        if let Some(menu) = &info.menu {
-            opts.merge(menu);
-        }
-        let mut first = true;
-        let mut first_row = None;
-        while let Ok(len) = buf.read_line({
-            line.clear();
-            &mut line
-        }) {
-            let line = &line[..len];
-            if line.is_empty() {
-                break;
-            }
-            if enable_debug {
-                eprintln!("got a row {line:?}");
-            }
-            match json5::from_str::<Row>(line) {
-                Ok(row) => {
-                    if first {
-                        if row.to_rofi().is_some() {
-                            first_row = Some(row);
-                            first = false;
-                        }
-                    } else if let Some(row) = row.to_rofi() {
-                        if let Some(row) = first_row.take() {
-                            out.write_all(opts.to_rofi().as_bytes())
-                                .expect("failed writing menu options into stdout");
-                            out.write_all(row.to_rofi().unwrap().as_bytes())
-                                .expect("failed writing into stdout");
-                        }
-                        out.write_all(&[DELIM as u8])
-                            .expect("failed writing into stdout");
-                        out.write_all(row.as_bytes())
-                            .expect("failed writing into stdout");
-                    }
-                }
-                Err(err) => {
-                    eprintln!("row parse error ({line}):\n{err}");
-                }
-            }
-        }
-        if let Some(row) = first_row.take() {
-            if opts.autoselect {
-                info = row.info;
-                data = opts.data;
-                input = row.text;
-                continue;
-            } else {
-                out.write_all(opts.to_rofi().as_bytes())
-                    .expect("failed writing menu options into stdout");
-                out.write_all(row.to_rofi().unwrap().as_bytes())
-                    .expect("failed writing into stdout");
-            }
+    log::record(log::Level::Debug, |x| {
+        x.rofi = Some(log::Rofi {
+            retv: env::var("ROFI_RETV").ok(),
+            info: info.clone(),
+            data: data.clone(),
+        });
+    });
+    let data: Data = match (data, resume) {
+        (None, Some(name)) => session::load(&name),
+        (data, _) => json5::from_str(&data.unwrap_or_default()).unwrap_or_default(),
+    };
+    let action = Action {
+        retv,
+        input: input.unwrap_or_default(),
+        row: info.is_some(),
+        status: None,
+        output: None,
+    };
+    let info: Info = info
+        .as_deref()
+        .map_or_else(
+            || data.fallback.clone().unwrap_or_default(),
+            |info| json5::from_str(info).expect("failed to parse info"),
+        )
+        .for_retv(retv);
+    let Some(menu) = engine::step(data, info, action, &mut Servers::default()) else {
+        return;
+    };
+    out.write_all(menu.opts.to_rofi().as_bytes())
+        .expect("failed writing menu options into stdout");
+    for (i, row) in menu.rows.iter().enumerate() {
+        if i != 0 {
+            out.write_all(&[DELIM as u8])
+                .expect("failed writing into stdout");
         }
-        break;
+        out.write_all(row.to_rofi().unwrap().as_bytes())
+            .expect("failed writing into stdout");
     }
 }
@@ -0,0 +1,102 @@
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+use crate::{error::Error, frame::Frame, options::ModeOptions, row::Row};
+
+const EXTENSIONS: &[&str] = &["json5", "json", "toml"];
+
+/// Fields that are only shown to the user, the only ones where `{stack.N}`
+/// is replaced (operations use placeholders instead, so that the stack
+/// can't inject commands)
+const TEXT_FIELDS: &[&str] = &["text", "display", "prompt", "message", "meta"];
+
+/// Contents of a static menu file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MenuFile {
+    #[serde(default)]
+    options: Option<Value>,
+    #[serde(default)]
+    rows: Vec<Value>,
+}
+
+/// Whether this call stack entry is a menu file rather than a script
+pub fn is_menu_file(entry: &str) -> bool {
+    Path::new(entry)
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| EXTENSIONS.contains(&x))
+}
+
+/// Replace `{stack.N}` in every string with the value N deep in the stack
+fn substitute_all(value: &mut Value, re: &Regex, stack: &[String]) {
+    match value {
+        Value::String(s) => {
+            *s = re
+                .replace_all(s, |caps: &Captures| {
+                    caps[1]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| stack.iter().rev().nth(i))
+                        .cloned()
+                        .unwrap_or_default()
+                })
+                .into_owned();
+        }
+        Value::Array(v) => {
+            for x in v {
+                substitute_all(x, re, stack);
+            }
+        }
+        Value::Object(m) => {
+            for x in m.values_mut() {
+                substitute_all(x, re, stack);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace `{stack.N}` in the text fields of a row or the menu options
+fn substitute(value: &mut Value, re: &Regex, stack: &[String]) {
+    if let Value::Object(m) = value {
+        for key in TEXT_FIELDS {
+            if let Some(x) = m.get_mut(*key) {
+                substitute_all(x, re, stack);
+            }
+        }
+    }
+}
+
+/// Load the menu from a file, without running anything
+pub fn load(frame: &Frame, stack: &[String]) -> Result<(ModeOptions, Vec<Row>), Error> {
+    let path = &frame.script;
+    let err = |err: String| Error::File {
+        script: path.clone(),
+        err,
+    };
+    let text = fs::read_to_string(path).map_err(|x| err(x.to_string()))?;
+    let file: MenuFile = if path.ends_with(".toml") {
+        toml::from_str(&text).map_err(|x| err(x.to_string()))?
+    } else {
+        json5::from_str(&text).map_err(|x| err(x.to_string()))?
+    };
+    let re = Regex::new(r"\{stack\.([0-9]+)\}").unwrap();
+    let mut opts = file
+        .options
+        .unwrap_or_else(|| Value::Object(Default::default()));
+    substitute(&mut opts, &re, stack);
+    let opts =
+        ModeOptions::deserialize(opts).map_err(|x| err(format!("invalid menu options: {x}")))?;
+    let mut rows = vec![];
+    for (i, mut row) in file.rows.into_iter().enumerate() {
+        substitute(&mut row, &re, stack);
+        let row = Row::deserialize(row).map_err(|x| err(format!("invalid row #{}: {x}", i + 1)))?;
+        if row.to_rofi().is_some() {
+            rows.push(row);
+        }
+    }
+    Ok((opts, rows))
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, process::Command};
+use zbus::{blocking::Connection, zvariant::Value};
+
+use crate::{log, trace, Context, VecString};
+
+/// Desktop notification to show once `exec` completes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notify {
+    pub summary: VecString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<VecString>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urgency: Option<Urgency>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl Notify {
+    /// Show the notification, over the session bus if possible and with
+    /// notify-send otherwise
+    pub fn send(&self, ctx: &Context) {
+        if trace::replaying() {
+            return;
+        }
+        let summary = self.summary.flatten(ctx);
+        let body = self
+            .body
+            .as_ref()
+            .map(|x| x.flatten(ctx))
+            .unwrap_or_default();
+        if let Err(err) = self.send_dbus(&summary, &body) {
+            log::error(format!(
+                "failed to notify over dbus, falling back to notify-send: {err}"
+            ));
+            let mut cmd = Command::new("notify-send");
+            cmd.arg("--app-name=rofi-menu-stack");
+            if let Some(urgency) = self.urgency {
+                cmd.arg(format!("--urgency={}", urgency.name()));
+            }
+            cmd.arg("--").arg(summary);
+            if !body.is_empty() {
+                cmd.arg(body);
+            }
+            log::command(&cmd);
+            match cmd.status() {
+                Ok(status) if status.success() => {}
+                Ok(status) => log::error(format!("notify-send: {status}")),
+                Err(err) => log::error(format!("failed to run notify-send: {err}")),
+            }
+        }
+    }
+
+    fn send_dbus(&self, summary: &str, body: &str) -> zbus::Result<()> {
+        let conn = Connection::session()?;
+        let mut hints = HashMap::new();
+        if let Some(urgency) = self.urgency {
+            hints.insert("urgency", Value::U8(urgency as u8));
+        }
+        conn.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "rofi-menu-stack",
+                0u32,
+                "",
+                summary,
+                body,
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )?;
+        Ok(())
+    }
+}
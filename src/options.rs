@@ -2,6 +2,8 @@ use serde::{de::Visitor, ser::SerializeStruct};
 use serde::{Deserialize, Serialize};
 
 use crate::fallback_row::FallbackRow;
+use crate::session;
+use crate::widget::Number;
 use crate::{Data, DELIM};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -11,8 +13,28 @@ pub enum Markup {
 
 impl Markup {
     const ITEMS: &[&'static str] = &["pango"];
+
+    /// Escape text for Pango, which rofi always uses for the message
+    pub fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Sort {
+    /// Most frequently and recently selected rows first
+    Frecency,
+}
+
+impl Sort {
+    const ITEMS: &[&'static str] = &["frecency"];
+}
+
+/// Menus the engine fills in
+const KINDS: &[&str] = &["number"];
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Selection {
     /// Keep previously selected item
@@ -75,6 +97,14 @@ impl<'a> Visitor<'a> for SelectionVisitor {
     {
         Ok(Selection::Set(v))
     }
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        i64::try_from(v)
+            .map(Selection::Set)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+    }
 }
 
 impl<'a> Deserialize<'a> for Selection {
@@ -97,6 +127,35 @@ pub struct ModeOptions {
     pub data: Data,
     /// Whether to autoselect the only item if there's only one item
     pub autoselect: bool,
+    /// Whether to pass custom keybindings to the script
+    pub hotkeys: bool,
+    /// Indices of rows to mark as urgent
+    pub urgent: Option<Vec<usize>>,
+    /// Indices of rows to mark as active
+    pub active: Option<Vec<usize>>,
+    /// Theme snippet to apply to the menu
+    pub theme: Option<String>,
+    /// Whether to keep the filter text after selecting a row
+    pub keep_filter: bool,
+    /// How to reorder the rows
+    pub sort: Option<Sort>,
+    /// Number input menu (`kind: "number"`)
+    pub number: Option<Number>,
+    /// How many rows to show at once
+    pub page_size: Option<usize>,
+}
+
+fn write_indices(ret: &mut String, key: &str, indices: &[usize]) {
+    ret.push('\0');
+    ret.push_str(key);
+    ret.push('\x1F');
+    for (i, x) in indices.iter().enumerate() {
+        if i != 0 {
+            ret.push(',');
+        }
+        ret.push_str(&x.to_string());
+    }
+    ret.push(DELIM);
 }
 
 impl ModeOptions {
@@ -116,9 +175,36 @@ impl ModeOptions {
         if let Some(fallback) = &other.data.fallback {
             self.data.fallback = Some(fallback.clone());
         }
+        if let Some(session) = &other.data.session {
+            self.data.session = Some(session.clone());
+        }
         if other.autoselect {
             self.autoselect = true;
         }
+        if other.hotkeys {
+            self.hotkeys = true;
+        }
+        if let Some(urgent) = &other.urgent {
+            self.urgent = Some(urgent.clone());
+        }
+        if let Some(active) = &other.active {
+            self.active = Some(active.clone());
+        }
+        if let Some(theme) = &other.theme {
+            self.theme = Some(theme.clone());
+        }
+        if other.keep_filter {
+            self.keep_filter = true;
+        }
+        if let Some(sort) = other.sort {
+            self.sort = Some(sort);
+        }
+        if let Some(number) = &other.number {
+            self.number = Some(number.clone());
+        }
+        if let Some(page_size) = other.page_size {
+            self.page_size = Some(page_size);
+        }
     }
     const FIELDS: &[&'static str] = &[
         "prompt",
@@ -127,6 +213,20 @@ impl ModeOptions {
         "allow-custom",
         "selection",
         "autoselect",
+        "hotkeys",
+        "urgent",
+        "active",
+        "theme",
+        "keep_filter",
+        "session",
+        "sort",
+        "kind",
+        "min",
+        "max",
+        "step",
+        "value_cmd",
+        "set",
+        "page_size",
     ];
     pub fn to_rofi(&self) -> String {
         let mut ret = String::new();
@@ -166,6 +266,25 @@ impl ModeOptions {
                 ret.push(DELIM);
             }
         }
+        if self.hotkeys {
+            ret.push_str("\0use-hot-keys\x1Ftrue");
+            ret.push(DELIM);
+        }
+        if let Some(urgent) = &self.urgent {
+            write_indices(&mut ret, "urgent", urgent);
+        }
+        if let Some(active) = &self.active {
+            write_indices(&mut ret, "active", active);
+        }
+        if let Some(theme) = &self.theme {
+            ret.push_str("\0theme\x1F");
+            ret.push_str(theme);
+            ret.push(DELIM);
+        }
+        if self.keep_filter {
+            ret.push_str("\0keep-filter\x1Ftrue");
+            ret.push(DELIM);
+        }
         ret.push_str("\0data\x1F");
         ret.push_str(&json5::to_string(&self.data).expect("failed to serialize data"));
         ret.push(DELIM);
@@ -181,6 +300,8 @@ impl<'a> Visitor<'a> for ModeOptionsVisitor {
     }
     fn visit_map<A: serde::de::MapAccess<'a>>(self, mut map: A) -> Result<Self::Value, A::Error> {
         let mut ret = Self::Value::default();
+        let mut number = None::<Number>;
+        let mut is_number = false;
         while let Some(key) = map.next_key::<String>()? {
             match key.as_str() {
                 "prompt" => ret.prompt = Some(map.next_value()?),
@@ -192,9 +313,49 @@ impl<'a> Visitor<'a> for ModeOptionsVisitor {
                 "fallback" => ret.data.fallback = Some(map.next_value::<FallbackRow>()?.0),
                 "select" | "selection" => ret.selection = Some(map.next_value()?),
                 "autoselect" => ret.autoselect = map.next_value()?,
+                "hotkeys" => ret.hotkeys = map.next_value()?,
+                "urgent" => ret.urgent = Some(map.next_value()?),
+                "active" => ret.active = Some(map.next_value()?),
+                "theme" => ret.theme = Some(map.next_value()?),
+                "keep_filter" => ret.keep_filter = map.next_value()?,
+                "session" => {
+                    let name: String = map.next_value()?;
+                    if !session::valid_name(&name) {
+                        return Err(serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(&name),
+                            &"a session name without `/` or `..`",
+                        ));
+                    }
+                    ret.data.session = Some(name);
+                }
+                "sort" => match map.next_value::<String>()?.as_str() {
+                    "frecency" => ret.sort = Some(Sort::Frecency),
+                    key => return Err(serde::de::Error::unknown_variant(key, Sort::ITEMS)),
+                },
+                "kind" => match map.next_value::<String>()?.as_str() {
+                    "number" => is_number = true,
+                    key => return Err(serde::de::Error::unknown_variant(key, KINDS)),
+                },
+                "min" => number.get_or_insert_with(Number::default).min = Some(map.next_value()?),
+                "max" => number.get_or_insert_with(Number::default).max = Some(map.next_value()?),
+                "step" => number.get_or_insert_with(Number::default).step = Some(map.next_value()?),
+                "value_cmd" => {
+                    number.get_or_insert_with(Number::default).value_cmd = Some(map.next_value()?);
+                }
+                "set" => number.get_or_insert_with(Number::default).set = map.next_value()?,
+                "page_size" => ret.page_size = Some(map.next_value()?),
                 key => return Err(serde::de::Error::unknown_field(key, Self::Value::FIELDS)),
             }
         }
+        match (is_number, number) {
+            (true, number) => ret.number = Some(number.unwrap_or_default()),
+            (false, Some(_)) => {
+                return Err(serde::de::Error::custom(
+                    "min, max, step, value_cmd and set require kind: \"number\"",
+                ))
+            }
+            (false, None) => {}
+        }
         Ok(ret)
     }
 }
@@ -222,7 +383,22 @@ impl Serialize for ModeOptions {
             + b2i(self.markup.is_some())
             + b2i(self.selection.is_some())
             + b2i(self.data.fallback.is_some())
-            + b2i(self.autoselect);
+            + b2i(self.autoselect)
+            + b2i(self.hotkeys)
+            + b2i(self.urgent.is_some())
+            + b2i(self.active.is_some())
+            + b2i(self.theme.is_some())
+            + b2i(self.keep_filter)
+            + b2i(self.data.session.is_some())
+            + b2i(self.sort.is_some())
+            + b2i(self.page_size.is_some())
+            + self.number.as_ref().map_or(0, |x| {
+                1 + b2i(x.min.is_some())
+                    + b2i(x.max.is_some())
+                    + b2i(x.step.is_some())
+                    + b2i(x.value_cmd.is_some())
+                    + b2i(!x.set.is_empty())
+            });
         let mut s = serializer.serialize_struct("ModeOption", len)?;
         if let Some(prompt) = &self.prompt {
             s.serialize_field("prompt", &prompt)?;
@@ -245,6 +421,49 @@ impl Serialize for ModeOptions {
         if self.autoselect {
             s.serialize_field("autoselect", &true)?;
         }
+        if self.hotkeys {
+            s.serialize_field("hotkeys", &true)?;
+        }
+        if let Some(urgent) = &self.urgent {
+            s.serialize_field("urgent", urgent)?;
+        }
+        if let Some(active) = &self.active {
+            s.serialize_field("active", active)?;
+        }
+        if let Some(theme) = &self.theme {
+            s.serialize_field("theme", theme)?;
+        }
+        if self.keep_filter {
+            s.serialize_field("keep_filter", &true)?;
+        }
+        if let Some(session) = &self.data.session {
+            s.serialize_field("session", session)?;
+        }
+        match self.sort {
+            None => {}
+            Some(Sort::Frecency) => s.serialize_field("sort", "frecency")?,
+        }
+        if let Some(page_size) = self.page_size {
+            s.serialize_field("page_size", &page_size)?;
+        }
+        if let Some(number) = &self.number {
+            s.serialize_field("kind", "number")?;
+            if let Some(min) = number.min {
+                s.serialize_field("min", &min)?;
+            }
+            if let Some(max) = number.max {
+                s.serialize_field("max", &max)?;
+            }
+            if let Some(step) = number.step {
+                s.serialize_field("step", &step)?;
+            }
+            if let Some(value_cmd) = &number.value_cmd {
+                s.serialize_field("value_cmd", value_cmd)?;
+            }
+            if !number.set.is_empty() {
+                s.serialize_field("set", &number.set)?;
+            }
+        }
         s.end()
     }
 }
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    options::{ModeOptions, Selection},
+    row::Row,
+};
+
+/// Page to switch to in a paginated menu
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Page {
+    Next,
+    Previous,
+}
+
+impl Page {
+    /// Page number after switching from `page`
+    pub fn apply(self, page: usize) -> usize {
+        match self {
+            Self::Next => page + 1,
+            Self::Previous => page.saturating_sub(1),
+        }
+    }
+}
+
+fn page_row(text: String, page: Page) -> Row {
+    let mut ret = Row {
+        text,
+        permanent: true,
+        ..Row::default()
+    };
+    ret.info.page = Some(page);
+    ret.info.generated = true;
+    ret
+}
+
+/// Only keep the rows of the current page and add rows to switch pages,
+/// keeping the row indices in the options pointing at the same rows
+pub fn paginate(opts: &mut ModeOptions, rows: &mut Vec<Row>) {
+    let Some(size) = opts.page_size.filter(|x| *x > 0) else {
+        opts.data.page = 0;
+        return;
+    };
+    let pages = rows.len().div_ceil(size).max(1);
+    let page = opts.data.page.min(pages - 1);
+    opts.data.page = page;
+    if pages == 1 {
+        return;
+    }
+    let start = page * size;
+    let end = rows.len().min(start + size);
+    let offset = usize::from(page > 0);
+    let index = |i: usize| (start..end).contains(&i).then(|| i - start + offset);
+    for x in [&mut opts.urgent, &mut opts.active].into_iter().flatten() {
+        *x = x.iter().filter_map(|i| index(*i)).collect();
+    }
+    if let Some(Selection::Set(x)) = opts.selection {
+        opts.selection = usize::try_from(x)
+            .ok()
+            .and_then(index)
+            .map(|x| Selection::Set(x as i64));
+    }
+    let mut ret = Vec::with_capacity(end - start + 2);
+    if page > 0 {
+        ret.push(page_row(
+            format!("Previous page ({page}/{pages})"),
+            Page::Previous,
+        ));
+    }
+    ret.extend(rows.drain(start..end));
+    if page + 1 < pages {
+        ret.push(page_row(
+            format!("Next page ({}/{pages})", page + 2),
+            Page::Next,
+        ));
+    }
+    *rows = ret;
+}
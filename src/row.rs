@@ -1,14 +1,39 @@
-use serde::{de::Visitor, Deserialize};
+use serde::{de::Visitor, Deserialize, Serialize};
 
-use crate::{Info, ModeOptions};
+use crate::{
+    condition::Condition,
+    fallback_row::{FallbackRow, Keys},
+    widget::Kind,
+    Confirm, Info, ModeOptions, VecString,
+};
 
+#[derive(Clone, Debug, Serialize)]
 pub struct Row {
     pub text: String,
+    /// Text to show instead of `text` (`text` is still used for filtering)
+    pub display: Option<String>,
     pub icon: String,
     pub meta: String,
     pub selectable: bool,
     pub urgent: bool,
     pub active: bool,
+    /// Whether to show the row regardless of the filter
+    pub permanent: bool,
+    /// Checkbox or radio rows get their markers and commands from their state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    /// Whether the setting is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<bool>,
+    /// Command that exits successfully if the setting is on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_cmd: Option<String>,
+    /// Command to switch the setting on
+    #[serde(skip_serializing_if = "VecString::is_empty")]
+    pub on: VecString,
+    /// Command to switch the setting off
+    #[serde(skip_serializing_if = "VecString::is_empty")]
+    pub off: VecString,
     pub info: Info,
 }
 
@@ -16,12 +41,19 @@ impl Default for Row {
     fn default() -> Self {
         Self {
             text: String::new(),
+            display: None,
             icon: String::new(),
             meta: String::new(),
             selectable: true,
             info: Info::default(),
             urgent: false,
             active: false,
+            permanent: false,
+            kind: None,
+            state: None,
+            state_cmd: None,
+            on: VecString::default(),
+            off: VecString::default(),
         }
     }
 }
@@ -29,11 +61,18 @@ impl Default for Row {
 impl Row {
     const FIELDS: &[&'static str] = &[
         "text",
+        "display",
         "icon",
         "meta",
         "selectable",
         "urgent",
         "active",
+        "permanent",
+        "kind",
+        "state",
+        "state_cmd",
+        "on",
+        "off",
         "push",
         "pop",
         "jump",
@@ -41,7 +80,20 @@ impl Row {
         "return",
         "exec",
         "fork",
+        "capture",
+        "on_success",
+        "on_failure",
+        "notify",
+        "confirm",
+        "toggle",
+        "apply",
+        "page",
         "menu",
+        "keys",
+        "set",
+        "unset",
+        "if",
+        "else",
     ];
 
     pub fn info(&self) -> String {
@@ -54,6 +106,11 @@ impl Row {
         }
         let mut ret = self.text.clone();
         ret.push('\0');
+        if let Some(display) = &self.display {
+            ret.push_str("display\x1F");
+            ret.push_str(display);
+            ret.push('\x1F');
+        }
         if !self.icon.is_empty() {
             ret.push_str("icon\x1F");
             ret.push_str(&self.icon);
@@ -76,6 +133,9 @@ impl Row {
         if self.active {
             ret.push_str("active\x1Ftrue\x1F");
         }
+        if self.permanent {
+            ret.push_str("permanent\x1Ftrue\x1F");
+        }
         ret.pop();
         Some(ret)
     }
@@ -113,11 +173,18 @@ impl<'a> Visitor<'a> for RowVisitor {
         while let Some(key) = map.next_key::<String>()? {
             match key.as_str() {
                 "text" => ret.text = map.next_value()?,
+                "display" => ret.display = Some(map.next_value()?),
                 "icon" => ret.icon = map.next_value()?,
                 "meta" => ret.meta = map.next_value()?,
                 "selectable" => ret.selectable = map.next_value()?,
                 "urgent" => ret.urgent = map.next_value()?,
                 "active" => ret.active = map.next_value()?,
+                "permanent" => ret.permanent = map.next_value()?,
+                "kind" => ret.kind = Some(map.next_value()?),
+                "state" => ret.state = Some(map.next_value()?),
+                "state_cmd" => ret.state_cmd = Some(map.next_value()?),
+                "on" => ret.on = map.next_value()?,
+                "off" => ret.off = map.next_value()?,
                 "push" => ret.info.push = map.next_value()?,
                 "pop" => ret.info.pop = map.next_value()?,
                 "jump" => ret.info.push_call = map.next_value()?,
@@ -130,7 +197,30 @@ impl<'a> Visitor<'a> for RowVisitor {
                 "return" => ret.info.pop_call = map.next_value()?,
                 "exec" => ret.info.exec = map.next_value()?,
                 "fork" => ret.info.fork = map.next_value()?,
+                "capture" => ret.info.capture = Some(map.next_value()?),
+                "notify" => ret.info.notify = Some(map.next_value()?),
+                "toggle" => ret.info.toggle = map.next_value()?,
+                "apply" => ret.info.apply = Some(map.next_value()?),
+                "page" => ret.info.page = Some(map.next_value()?),
+                "confirm" => {
+                    ret.info.confirm = Some(Confirm {
+                        message: map.next_value()?,
+                        row: None,
+                        action: None,
+                    });
+                }
+                "on_success" => {
+                    ret.info.on_success = Some(map.next_value::<FallbackRow>()?.0.into());
+                }
+                "on_failure" => {
+                    ret.info.on_failure = Some(map.next_value::<FallbackRow>()?.0.into());
+                }
                 "menu" => ret.info.menu = Some(map.next_value::<ModeOptions>()?.into()),
+                "keys" => ret.info.keys = map.next_value::<Keys>()?.0,
+                "set" => ret.info.set = map.next_value()?,
+                "unset" => ret.info.unset = map.next_value()?,
+                "if" => ret.info.cond = Some(Condition::parse(&mut map)?),
+                "else" => ret.info.otherwise = Some(map.next_value::<FallbackRow>()?.0.into()),
                 key => return Err(serde::de::Error::unknown_field(key, Self::Value::FIELDS)),
             }
         }
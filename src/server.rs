@@ -0,0 +1,300 @@
+use serde::Serialize;
+use std::{
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        BTreeMap, HashMap,
+    },
+    env,
+    fs::{self, DirBuilder},
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, Write},
+    os::{
+        fd::AsRawFd,
+        unix::{
+            fs::{DirBuilderExt, MetadataExt},
+            net::{UnixListener, UnixStream},
+        },
+    },
+    path::PathBuf,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::{engine::detach, frame::Frame, log};
+
+/// Call stack entries starting with this prefix are run as long-running servers
+pub const PREFIX: &str = "server:";
+
+/// A single menu request, sent to the server as a line of JSON
+#[derive(Serialize)]
+pub struct Request<'a> {
+    pub call_stack: &'a [Frame],
+    pub stack: &'a [String],
+    pub input: &'a str,
+    pub retv: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+    pub vars: BTreeMap<&'a str, &'a str>,
+}
+
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Server {
+    /// Start the server with the args, cwd and env of `frame`
+    fn spawn(script: &str, frame: &Frame) -> io::Result<Self> {
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c")
+            .arg("\"$0\" \"$@\"")
+            .arg(frame.resolve(script))
+            .args(&frame.args)
+            .envs(&frame.env)
+            .env("RMS_SERVER", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        if let Some(cwd) = &frame.cwd {
+            cmd.current_dir(cwd);
+        }
+        log::command(&cmd);
+        let mut child = cmd.spawn()?;
+        let stdin = child.stdin.take().expect("server is missing stdin?");
+        let stdout = BufReader::new(child.stdout.take().expect("server is missing stdout?"));
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// Send a request line and forward the response (including the terminator) to `out`
+    fn request(&mut self, line: &str, out: &mut impl Write) -> io::Result<()> {
+        self.stdin.write_all(line.as_bytes())?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()?;
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            if self.stdout.read_line(&mut buf)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            out.write_all(buf.as_bytes())?;
+            if buf.trim_end_matches('\n').is_empty() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Registry of long-running menu scripts.
+///
+/// Local servers live as long as this process does, which is what the
+/// standalone driver wants. Otherwise (in rofi's script mode, where every
+/// step is a new process) each server is owned by a broker process that
+/// lives as long as rofi and is reached through a unix socket.
+#[derive(Default)]
+pub struct Servers {
+    local: bool,
+    procs: HashMap<String, Server>,
+}
+
+impl Servers {
+    pub fn local() -> Self {
+        Self {
+            local: true,
+            procs: HashMap::new(),
+        }
+    }
+
+    /// Send a request to a server script, returns the response lines (without the terminator)
+    pub fn request(
+        &mut self,
+        script: &str,
+        frame: &Frame,
+        req: &Request,
+    ) -> io::Result<Vec<String>> {
+        let line = serde_json::to_string(req).expect("failed to serialize server request");
+        let mut out = vec![];
+        if self.local {
+            let key = key(script, frame);
+            let server = match self.procs.entry(key.clone()) {
+                Entry::Occupied(x) => x.into_mut(),
+                Entry::Vacant(x) => x.insert(Server::spawn(script, frame)?),
+            };
+            if let Err(err) = server.request(&line, &mut out) {
+                self.procs.remove(&key);
+                return Err(err);
+            }
+        } else {
+            let mut stream = connect(script, frame)?;
+            stream.write_all(line.as_bytes())?;
+            stream.write_all(b"\n")?;
+            let mut stream = BufReader::new(stream);
+            let mut buf = String::new();
+            loop {
+                buf.clear();
+                if stream.read_line(&mut buf)? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                out.extend_from_slice(buf.as_bytes());
+                if buf.trim_end_matches('\n').is_empty() {
+                    break;
+                }
+            }
+        }
+        let out = String::from_utf8(out)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let mut lines: Vec<String> = out.lines().map(str::to_owned).collect();
+        lines.pop();
+        Ok(lines)
+    }
+}
+
+/// Directory for broker sockets that only the current user can access
+fn socket_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(dir.into());
+    }
+    let uid = unsafe { libc::getuid() };
+    let dir = env::temp_dir().join(format!("rofi-menu-stack-{uid}"));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => return Err(err),
+        _ => {}
+    }
+    // anyone could have created it first
+    let meta = fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Servers are started once for every script with the same args, cwd and env
+fn key(script: &str, frame: &Frame) -> String {
+    serde_json::to_string(&(script, &frame.args, &frame.cwd, &frame.env))
+        .expect("failed to serialize server key")
+}
+
+fn socket_path(key: &str, owner: u32) -> io::Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    Ok(socket_dir()?.join(format!(
+        "rofi-menu-stack-{owner}-{:016x}.sock",
+        hasher.finish()
+    )))
+}
+
+/// User on the other end of a socket
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// User on the other end of a socket
+#[cfg(not(target_os = "linux"))]
+fn peer_uid(stream: &UnixStream) -> io::Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Make sure the other end of a socket belongs to the current user
+fn check_peer(stream: &UnixStream) -> io::Result<()> {
+    if peer_uid(stream)? != unsafe { libc::getuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "server socket belongs to another user",
+        ));
+    }
+    Ok(())
+}
+
+/// Connect to the broker for this script, starting it if necessary
+fn connect(script: &str, frame: &Frame) -> io::Result<UnixStream> {
+    // the broker belongs to the rofi instance that started us
+    let owner = std::os::unix::process::parent_id();
+    let path = socket_path(&key(script, frame), owner)?;
+    if let Ok(stream) = UnixStream::connect(&path) {
+        check_peer(&stream)?;
+        return Ok(stream);
+    }
+    let _ = fs::remove_file(&path);
+    // bind before forking so the connection below can't race the broker
+    let listener = UnixListener::bind(&path)?;
+    if detach() {
+        broker(script, frame, owner, listener);
+        let _ = fs::remove_file(&path);
+        std::process::exit(0);
+    }
+    drop(listener);
+    let stream = UnixStream::connect(&path)?;
+    check_peer(&stream)?;
+    Ok(stream)
+}
+
+fn broker(script: &str, frame: &Frame, owner: u32, listener: UnixListener) {
+    let Ok(mut server) = Server::spawn(script, frame) else {
+        return;
+    };
+    if listener.set_nonblocking(true).is_err() {
+        return;
+    }
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if check_peer(&stream).is_err() {
+                    continue;
+                }
+                let _ = stream.set_nonblocking(false);
+                let mut line = String::new();
+                let mut reader = BufReader::new(&stream);
+                if reader.read_line(&mut line).is_err() {
+                    continue;
+                }
+                if server
+                    .request(line.trim_end_matches('\n'), &mut &stream)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                // stop once rofi is gone
+                if unsafe { libc::kill(owner as libc::pid_t, 0) } != 0 {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(_) => break,
+        }
+    }
+    drop(server.stdin);
+    let _ = server.child.wait();
+}
@@ -0,0 +1,77 @@
+use std::{env, fs, path::PathBuf, sync::OnceLock};
+
+use crate::{log, Data};
+
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep the state in another directory from now on
+pub fn set_state_dir(dir: PathBuf) {
+    let _ = STATE_DIR.set(dir);
+}
+
+/// Directory for state that should persist across runs
+pub fn state_dir() -> PathBuf {
+    if let Some(dir) = STATE_DIR.get() {
+        return dir.clone();
+    }
+    let dir = env::var_os("XDG_STATE_HOME").map_or_else(
+        || {
+            PathBuf::from(env::var_os("HOME").expect("HOME must be set"))
+                .join(".local")
+                .join("state")
+        },
+        PathBuf::from,
+    );
+    dir.join("rofi-menu-stack")
+}
+
+/// Whether a session name is safe to use as a file name in the state
+/// directory
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\0']) && !name.contains("..")
+}
+
+fn path(name: &str) -> Option<PathBuf> {
+    if !valid_name(name) {
+        log::error(format!("invalid session name {name:?}"));
+        return None;
+    }
+    Some(state_dir().join(format!("{name}.json")))
+}
+
+/// Load a saved session, or start a new one if there's none
+pub fn load(name: &str) -> Data {
+    let Some(path) = path(name) else {
+        return Data::default();
+    };
+    let mut data = match fs::read(&path) {
+        Ok(x) => serde_json::from_slice(&x).unwrap_or_else(|err| {
+            log::error(format!("failed to parse session {}: {err}", path.display()));
+            Data::default()
+        }),
+        Err(_) => Data::default(),
+    };
+    data.session = Some(name.to_owned());
+    data
+}
+
+/// Save the session, if the data belongs to one
+pub fn save(data: &Data) {
+    let Some(path) = data.session.as_deref().and_then(path) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let json = serde_json::to_vec(data).expect("failed to serialize session");
+    if let Err(err) = fs::write(&path, json) {
+        log::error(format!("failed to save session {}: {err}", path.display()));
+    }
+}
+
+/// Forget a session once its menu has been closed
+pub fn clear(data: &Data) {
+    if let Some(path) = data.session.as_deref().and_then(path) {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,157 @@
+use serde::Serialize;
+use std::{
+    env,
+    fs::{self, DirBuilder},
+    io::{stdout, Write},
+    os::unix::fs::DirBuilderExt,
+    process,
+};
+
+use crate::{
+    engine::{self, Menu},
+    options::ModeOptions,
+    row::Row,
+    server::Servers,
+    session, Action, Data, Info,
+};
+
+/// A scripted user action
+enum Choice {
+    /// Select the row at this index
+    Index(usize),
+    /// Select the first row with this text
+    Text(String),
+    /// Enter custom text
+    Input(String),
+}
+
+/// One simulated step, printed as JSON
+#[derive(Serialize)]
+struct Step<'a> {
+    choice: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    menu: Option<&'a ModeOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows: Option<&'a [Row]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<&'a Data>,
+    closed: bool,
+}
+
+/// Parse `index:<n>`, `text:<text>` or `input:<text>`, optionally prefixed
+/// with `key:<n>:` to use kb-custom-n, returns the choice and ROFI_RETV
+fn parse(choice: &str) -> Result<(Choice, Option<u8>), String> {
+    let (key, choice) = match choice.strip_prefix("key:") {
+        Some(rest) => {
+            let (n, rest) = rest
+                .split_once(':')
+                .ok_or_else(|| format!("{choice:?}: expected key:<n>:<choice>"))?;
+            match n.parse::<u8>() {
+                Ok(n @ 1..=19) => (Some(n + 9), rest),
+                _ => return Err(format!("{choice:?}: invalid custom keybinding {n:?}")),
+            }
+        }
+        None => (None, choice),
+    };
+    let ret = match choice.split_once(':') {
+        Some(("index", x)) => Choice::Index(
+            x.parse()
+                .map_err(|err| format!("{choice:?}: invalid index: {err}"))?,
+        ),
+        Some(("text", x)) => Choice::Text(x.to_owned()),
+        Some(("input", x)) => Choice::Input(x.to_owned()),
+        _ => {
+            return Err(format!(
+                "{choice:?}: expected index:<n>, text:<text> or input:<text>"
+            ))
+        }
+    };
+    Ok((ret, key))
+}
+
+fn print(choice: Option<&str>, menu: Option<&Menu>) {
+    let step = Step {
+        choice,
+        menu: menu.map(|x| &x.opts),
+        rows: menu.map(|x| &x.rows[..]),
+        data: menu.map(|x| &x.opts.data),
+        closed: menu.is_none(),
+    };
+    let mut out = stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &step).expect("failed writing into stdout");
+    out.write_all(b"\n").expect("failed writing into stdout");
+}
+
+/// Drive the menus with a list of choices the way rofi would, printing every menu
+pub fn run(data: Data, choices: &[String]) -> Result<(), String> {
+    // a dry run shouldn't change the user's sessions (the one to resume
+    // has already been loaded)
+    let dir = env::temp_dir().join(format!("rofi-menu-stack-simulate-{}", process::id()));
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|err| format!("{}: {err}", dir.display()))?;
+    session::set_state_dir(dir.clone());
+    let ret = steps(data, choices);
+    let _ = fs::remove_dir_all(&dir);
+    ret
+}
+
+fn steps(data: Data, choices: &[String]) -> Result<(), String> {
+    let mut servers = Servers::local();
+    let mut menu = engine::step(data, Info::default(), Action::default(), &mut servers);
+    print(None, menu.as_ref());
+    for choice in choices {
+        let Some(cur) = menu else {
+            return Err(format!("{choice:?}: the menu has already been closed"));
+        };
+        let (parsed, key) = parse(choice)?;
+        let index = match &parsed {
+            Choice::Index(i) if *i < cur.rows.len() => Some(*i),
+            Choice::Index(i) => return Err(format!("{choice:?}: there's no row {i}")),
+            Choice::Text(text) => Some(
+                cur.rows
+                    .iter()
+                    .position(|row| &row.text == text)
+                    .ok_or_else(|| format!("{choice:?}: there's no such row"))?,
+            ),
+            // rofi is told not to accept custom input without a fallback
+            Choice::Input(_) if cur.opts.data.fallback.is_none() => {
+                return Err(format!("{choice:?}: the menu doesn't accept custom input"));
+            }
+            Choice::Input(_) => None,
+        };
+        // pass everything through the same strings rofi would give us
+        let (info, input) = match (index, parsed) {
+            (Some(i), _) => {
+                let row = &cur.rows[i];
+                if !row.selectable {
+                    return Err(format!("{choice:?}: the row isn't selectable"));
+                }
+                (Some(row.info()), row.text.clone())
+            }
+            (None, Choice::Input(input)) => (None, input),
+            (None, _) => unreachable!(),
+        };
+        let retv = key.unwrap_or(if info.is_some() { 1 } else { 2 });
+        let data = json5::to_string(&cur.opts.data).expect("failed to serialize data");
+        let data: Data = json5::from_str(&data).expect("failed to parse data");
+        let action = Action {
+            retv,
+            input,
+            row: info.is_some(),
+            status: None,
+            output: None,
+        };
+        let info: Info = info
+            .as_deref()
+            .map_or_else(
+                || data.fallback.clone().unwrap_or_default(),
+                |info| json5::from_str(info).expect("failed to parse info"),
+            )
+            .for_retv(retv);
+        menu = engine::step(data, info, action, &mut servers);
+        print(Some(choice), menu.as_ref());
+    }
+    Ok(())
+}
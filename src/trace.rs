@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard, OnceLock,
+    },
+};
+
+use crate::{
+    engine::{self, Menu},
+    server::Servers,
+    Action, Data, Info,
+};
+
+/// Raw output of a script run during a step
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    pub script: String,
+    pub stdout: String,
+}
+
+/// Result of an `exec` command run during a step
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Exec {
+    status: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+}
+
+/// One step of a trace file
+#[derive(Serialize, Deserialize)]
+struct Record {
+    retv: u8,
+    input: String,
+    row: bool,
+    /// Operations of the selected row (or the fallback)
+    info: Info,
+    /// Data at the start of the step
+    before: Data,
+    /// INITIAL_SCRIPT and INITIAL_STACK, for steps that start the menu
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    outputs: Vec<Output>,
+    /// Commands aren't run again when replaying, their results are reused
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    execs: VecDeque<Exec>,
+    /// Row orders from frecency sorting, which depends on the local history
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    sorts: VecDeque<Vec<usize>>,
+    /// The rendered menu, `null` if it was closed
+    menu: Value,
+}
+
+static OUTPUTS: Mutex<Vec<Output>> = Mutex::new(Vec::new());
+static EXECS: Mutex<VecDeque<Exec>> = Mutex::new(VecDeque::new());
+static SORTS: Mutex<VecDeque<Vec<usize>>> = Mutex::new(VecDeque::new());
+static REPLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Trace file to append steps to, set with `RMS_TRACE`
+fn path() -> Option<&'static PathBuf> {
+    static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PATH.get_or_init(|| env::var_os("RMS_TRACE").map(PathBuf::from))
+        .as_ref()
+}
+
+fn recording() -> bool {
+    path().is_some() && !replaying()
+}
+
+/// Whether a trace is being replayed, in which case nothing outside of the
+/// menu should be changed (no commands, notifications, sessions or frecency)
+pub fn replaying() -> bool {
+    REPLAYING.load(Ordering::Relaxed)
+}
+
+/// Whether steps should be traced, if not, nothing needs to be recorded
+pub fn enabled() -> bool {
+    recording() || replaying()
+}
+
+fn take_outputs() -> Vec<Output> {
+    std::mem::take(&mut *OUTPUTS.lock().unwrap_or_else(|x| x.into_inner()))
+}
+
+fn execs() -> MutexGuard<'static, VecDeque<Exec>> {
+    EXECS.lock().unwrap_or_else(|x| x.into_inner())
+}
+
+/// Remember the result of an `exec` command
+pub fn exec(status: i32, output: Option<&str>) {
+    if recording() {
+        execs().push_back(Exec {
+            status,
+            output: output.map(str::to_owned),
+        });
+    }
+}
+
+/// When replaying, the recorded result of the next `exec` command
+/// (exit status 127 if it wasn't run when the trace was recorded)
+pub fn replayed_exec() -> Option<(i32, Option<String>)> {
+    replaying().then(|| {
+        execs()
+            .pop_front()
+            .map_or((127, None), |x| (x.status, x.output))
+    })
+}
+
+fn sorts() -> MutexGuard<'static, VecDeque<Vec<usize>>> {
+    SORTS.lock().unwrap_or_else(|x| x.into_inner())
+}
+
+/// Remember the order frecency sorting put the rows in (as indices of the
+/// script's rows)
+pub fn sort(order: &[usize]) {
+    if recording() {
+        sorts().push_back(order.to_vec());
+    }
+}
+
+/// When replaying, the recorded order of the next frecency sort (the
+/// script's order if there was none)
+pub fn replayed_sort() -> Option<Vec<usize>> {
+    replaying().then(|| sorts().pop_front().unwrap_or_default())
+}
+
+/// Remember the raw output of a script
+pub fn output(script: &str, stdout: &str) {
+    if enabled() {
+        OUTPUTS
+            .lock()
+            .unwrap_or_else(|x| x.into_inner())
+            .push(Output {
+                script: script.to_owned(),
+                stdout: stdout.to_owned(),
+            });
+    }
+}
+
+/// The parts of a menu that are compared when replaying
+fn render(menu: Option<&Menu>) -> Value {
+    match menu {
+        Some(menu) => serde_json::json!({
+            "menu": menu.opts,
+            "rows": menu.rows,
+            "data": menu.opts.data,
+        }),
+        None => Value::Null,
+    }
+}
+
+/// Append a step to the trace file
+pub fn record(before: Data, info: Info, action: &Action, menu: Option<&Menu>) {
+    // when replaying, the outputs are compared by `replay`
+    let Some(path) = path().filter(|_| recording()) else {
+        return;
+    };
+    let outputs = take_outputs();
+    let env = if before.call_stack.is_empty() {
+        ["INITIAL_SCRIPT", "INITIAL_STACK"]
+            .into_iter()
+            .filter_map(|k| Some((k.to_owned(), env::var(k).ok()?)))
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+    let record = Record {
+        retv: action.retv,
+        input: action.input.clone(),
+        row: action.row,
+        info,
+        before,
+        env,
+        outputs,
+        execs: std::mem::take(&mut *execs()),
+        sorts: std::mem::take(&mut *sorts()),
+        menu: render(menu),
+    };
+    let mut line = serde_json::to_vec(&record).expect("failed to serialize trace record");
+    line.push(b'\n');
+    let res = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&line));
+    if let Err(err) = res {
+        eprintln!("failed to write trace {}: {err}", path.display());
+    }
+}
+
+/// Find the first place where two values differ, as a path like `rows[1].text`
+fn diff(path: String, a: &Value, b: &Value) -> Option<(String, Value, Value)> {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            let keys = x.keys().chain(y.keys().filter(|k| !x.contains_key(*k)));
+            keys.into_iter().find_map(|k| {
+                let path = if path.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{path}.{k}")
+                };
+                let null = Value::Null;
+                diff(path, x.get(k).unwrap_or(&null), y.get(k).unwrap_or(&null))
+            })
+        }
+        (Value::Array(x), Value::Array(y)) if x.len() == y.len() => x
+            .iter()
+            .zip(y)
+            .enumerate()
+            .find_map(|(i, (a, b))| diff(format!("{path}[{i}]"), a, b)),
+        _ if a == b => None,
+        _ => Some((path, a.clone(), b.clone())),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("failed to serialize json")
+}
+
+/// Run the steps of a trace file again and report the first step whose
+/// menu is rendered differently
+pub fn replay(path: &str) -> Result<(), String> {
+    REPLAYING.store(true, Ordering::Relaxed);
+    let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    let mut servers = Servers::local();
+    let mut steps = 0;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(line)
+            .map_err(|err| format!("{path}: invalid record on line {}: {err}", i + 1))?;
+        if !record.env.is_empty() {
+            for k in ["INITIAL_SCRIPT", "INITIAL_STACK"] {
+                match record.env.get(k) {
+                    Some(v) => env::set_var(k, v),
+                    None => env::remove_var(k),
+                }
+            }
+        }
+        *execs() = record.execs;
+        *sorts() = record.sorts;
+        let action = Action {
+            retv: record.retv,
+            input: record.input,
+            row: record.row,
+            ..Action::default()
+        };
+        let menu = engine::step(record.before, record.info, action, &mut servers);
+        let outputs = take_outputs();
+        steps += 1;
+        let Some((at, recorded, replayed)) =
+            diff(String::new(), &record.menu, &render(menu.as_ref()))
+        else {
+            continue;
+        };
+        let mut report = format!(
+            "step {steps} (line {}): the menu differs at {}\nrecorded: {}\nreplayed: {}",
+            i + 1,
+            if at.is_empty() { "the top level" } else { &at },
+            describe(&recorded),
+            describe(&replayed),
+        );
+        if outputs != record.outputs {
+            for (what, outputs) in [("recorded", &record.outputs), ("replayed", &outputs)] {
+                report.push_str(&format!("\n{what} script output:"));
+                for x in outputs {
+                    report.push_str(&format!("\n{}:\n{}", x.script, x.stdout.trim_end()));
+                }
+            }
+        }
+        return Err(report);
+    }
+    println!("{steps} steps replayed, all menus match");
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+
+use crate::{
+    condition::{Condition, NumberRange},
+    engine::frame_command,
+    log,
+    options::{ModeOptions, Selection},
+    row::Row,
+    Data, Info, VecString,
+};
+
+/// Rows that show and change a setting
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// Selecting the row switches the setting on or off
+    Checkbox,
+    /// Selecting the row switches the setting on, one of a group
+    Radio,
+}
+
+impl Kind {
+    fn glyph(self, state: bool) -> &'static str {
+        match (self, state) {
+            (Self::Checkbox, true) => "☑",
+            (Self::Checkbox, false) => "☐",
+            (Self::Radio, true) => "◉",
+            (Self::Radio, false) => "○",
+        }
+    }
+}
+
+fn command(cmd: &str, data: &Data) -> Command {
+    let mut ret = frame_command(cmd, data);
+    ret.stdin(Stdio::null());
+    log::command(&ret);
+    ret
+}
+
+/// Run a state command, the setting is on if it exits successfully
+fn check_state(cmd: &str, data: &Data) -> bool {
+    command(cmd, data)
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|x| x.success())
+}
+
+/// Run a value command and parse its output as a number
+fn read_value(cmd: &str, data: &Data) -> Option<f64> {
+    let output = command(cmd, data).output().ok()?;
+    if !output.status.success() {
+        log::error(format!("{cmd}: {}", output.status));
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+impl Row {
+    /// Render a checkbox or radio row for the current state of its setting
+    pub fn render_kind(&mut self, data: &Data) {
+        let Some(kind) = self.kind else {
+            return;
+        };
+        let state = match (self.state, &self.state_cmd) {
+            (Some(x), _) => x,
+            (None, Some(cmd)) => check_state(cmd, data),
+            (None, None) => false,
+        };
+        let label = self.display.as_ref().unwrap_or(&self.text);
+        self.display = Some(format!("{} {label}", kind.glyph(state)));
+        self.active = state;
+        self.info.exec = match (kind, state) {
+            (_, false) => self.on.clone(),
+            (Kind::Checkbox, true) => self.off.clone(),
+            // selecting the current choice does nothing
+            (Kind::Radio, true) => Default::default(),
+        };
+    }
+}
+
+/// A menu for changing a number, by steps or by typing it in
+#[derive(Clone, Debug, Default)]
+pub struct Number {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// How much the generated rows change the value by (1 by default)
+    pub step: Option<f64>,
+    /// Command that prints the current value
+    pub value_cmd: Option<String>,
+    /// Command to set the value, user input placeholders are replaced
+    /// with the new value
+    pub set: VecString,
+}
+
+impl Number {
+    fn range(&self) -> NumberRange {
+        NumberRange {
+            min: self.min,
+            max: self.max,
+        }
+    }
+
+    /// Round to as many decimal places as the step and the current value
+    /// have, so that stepping doesn't show float errors like 0.30000000000000004
+    fn round(x: f64, step: f64, value: f64) -> f64 {
+        let decimals = |x: f64| x.to_string().split_once('.').map_or(0, |(_, x)| x.len());
+        format!("{x:.*}", decimals(step).max(decimals(value)))
+            .parse()
+            .unwrap_or(x)
+    }
+
+    fn clamp(&self, x: f64) -> f64 {
+        let x = self.min.map_or(x, |min| x.max(min));
+        self.max.map_or(x, |max| x.min(max))
+    }
+
+    /// Show the current value in the prompt, add rows that step the value
+    /// up and down and accept typed values in the range
+    pub fn render(&self, data: &Data, opts: &mut ModeOptions, rows: &mut Vec<Row>) {
+        let value = self
+            .value_cmd
+            .as_ref()
+            .and_then(|cmd| read_value(cmd, data));
+        if let Some(value) = value {
+            opts.prompt = Some(match &opts.prompt {
+                Some(prompt) => format!("{prompt} ({value})"),
+                None => value.to_string(),
+            });
+            let step = self.step.unwrap_or(1.0);
+            for (text, new) in [
+                (
+                    "Increase",
+                    self.clamp(Self::round(value + step, step, value)),
+                ),
+                (
+                    "Decrease",
+                    self.clamp(Self::round(value - step, step, value)),
+                ),
+            ] {
+                if new == value {
+                    continue;
+                }
+                let mut row = Row {
+                    text: text.to_owned(),
+                    display: Some(format!("{text} to {new}")),
+                    ..Row::default()
+                };
+                row.info.exec = self.set.with_input(&new.to_string());
+                row.info.generated = true;
+                row.info.menu = Some(
+                    ModeOptions {
+                        selection: Some(Selection::Set(rows.len() as i64)),
+                        ..ModeOptions::default()
+                    }
+                    .into(),
+                );
+                rows.push(row);
+            }
+        }
+        if opts.data.fallback.is_none() {
+            let range = self.range();
+            let message = match (range.min, range.max) {
+                (Some(min), Some(max)) => format!("Enter a number from {min} to {max}"),
+                (Some(min), None) => format!("Enter a number of at least {min}"),
+                (None, Some(max)) => format!("Enter a number of at most {max}"),
+                (None, None) => "Enter a number".to_owned(),
+            };
+            opts.data.fallback = Some(Info {
+                exec: self.set.clone(),
+                cond: Some(Condition {
+                    input_number: Some(range),
+                    ..Condition::default()
+                }),
+                otherwise: Some(Box::new(Info {
+                    menu: Some(
+                        ModeOptions {
+                            message: Some(message),
+                            ..ModeOptions::default()
+                        }
+                        .into(),
+                    ),
+                    ..Info::default()
+                })),
+                ..Info::default()
+            });
+        }
+    }
+}
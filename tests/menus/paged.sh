@@ -0,0 +1,8 @@
+#!/usr/bin/env bash
+. ./lib.sh
+options prompt '"Paged >"' page_size 3
+row '"a"'
+row '"b"'
+row '"c"'
+row '"d"' kind '"checkbox"' state_cmd '"test -e \"$STATE_DIR/d\""' on '"touch \"$STATE_DIR/d\""' off '"rm \"$STATE_DIR/d\""'
+row '"e"' confirm '"Sure?"'
@@ -0,0 +1,137 @@
+//! Golden file tests: run menus under `simulate` and compare the output
+//! with `tests/golden/<name>.json`. Set `UPDATE_GOLDEN=1` to regenerate them.
+
+use std::{
+    env, fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+fn simulate(name: &str, script: &str, stack: &str, choices: &[&str]) -> Output {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let state_dir = env::temp_dir().join(format!("rms-test-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&state_dir);
+    fs::create_dir_all(&state_dir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rofi-menu-stack"))
+        .current_dir(root)
+        .env("INITIAL_SCRIPT", script)
+        .env("INITIAL_STACK", stack)
+        .env("STATE_DIR", &state_dir)
+        .env("XDG_STATE_HOME", &state_dir)
+        .arg("simulate")
+        .args(choices)
+        .output()
+        .unwrap();
+    let _ = fs::remove_dir_all(&state_dir);
+    output
+}
+
+fn golden(name: &str, script: &str, stack: &str, choices: &[&str]) {
+    let output = simulate(name, script, stack, choices);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.json"));
+    let actual = String::from_utf8(output.stdout).unwrap();
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap();
+    assert!(
+        actual == expected,
+        "{name}: simulate output differs from {}",
+        path.display()
+    );
+}
+
+#[test]
+fn fib() {
+    golden(
+        "fib",
+        "sample/fib.sh",
+        r#"["0","1","0"]"#,
+        &["text:Next", "text:Next", "text:Next", "text:Close"],
+    );
+}
+
+/// Switching between the incrementor and the decrementor (without the
+/// forked commands that write value.txt)
+#[test]
+fn switch() {
+    golden(
+        "switch",
+        "sample/fib.sh",
+        r#"["0","1","0"]"#,
+        &[
+            "text:Next",
+            "text:Switch to incrementor",
+            "text:Switch to decrementor",
+            "text:Switch to incrementor",
+            "text:Close",
+        ],
+    );
+}
+
+#[test]
+fn debug() {
+    golden(
+        "debug",
+        "sample/debug.sh",
+        "[]",
+        &[
+            "input:a",
+            "input:b",
+            "text:!!!!Jump to self",
+            "text:!!!!Pop",
+            "text:!!!!Return",
+            "text:!!!!Return",
+        ],
+    );
+}
+
+#[test]
+fn counter_server() {
+    golden(
+        "counter_server",
+        "server:sample/counter_server.sh",
+        "[]",
+        &["text:Push", "text:Push", "text:Pop", "text:Close"],
+    );
+}
+
+/// Toggling a checkbox or answering "No" to a confirmation on the second
+/// page keeps that row selected
+#[test]
+fn paged_selection() {
+    golden(
+        "paged",
+        "tests/menus/paged.sh",
+        "[]",
+        &["text:Next page (2/2)", "text:d", "text:e", "text:No"],
+    );
+}
+
+/// The same with rows sorted by frecency
+#[test]
+fn frecency_selection() {
+    golden(
+        "frecency",
+        "tests/menus/frecency.sh",
+        "[]",
+        &["text:e", "text:Yes", "text:e", "text:No", "text:d"],
+    );
+}
+
+/// Rofi doesn't accept custom input in menus without a fallback
+#[test]
+fn input_without_fallback() {
+    let output = simulate("input", "tests/menus/paged.sh", "[]", &["input:a"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("doesn't accept custom input"), "{stderr}");
+}